@@ -4,6 +4,25 @@ use gtk::prelude::*;
 
 use relm4::Sender;
 
+/// An action registered through ActionBuilder or AccelManager, as listed
+/// in the command palette.
+#[derive(Debug, Clone)]
+pub struct RegisteredAction {
+    /// Like "global-navigation.down", to activate on the window it was added to.
+    pub name: String,
+    pub accels: Vec<String>,
+}
+
+thread_local! {
+    // Actions live on the GTK main thread, and so does the list of them.
+    static REGISTERED: RefCell<Vec<RegisteredAction>> = const { RefCell::new(Vec::new()) };
+}
+
+/// All the actions registered so far, in the order they were added.
+pub fn registered_actions() -> Vec<RegisteredAction> {
+    REGISTERED.with(|registered| registered.borrow().clone())
+}
+
 /// A helper for building keyboard-accelerated stateless actions.
 /// The relm4 way of creating actions is quite baroque, with creating
 /// a new type for each action. Let's skip that, use gtk-rs directly.
@@ -35,6 +54,12 @@ impl ActionBuilder {
         self.group.add_action_entries([sa]);
         let compound_name = format!("{}.{}", self.name, name);
         relm4::main_application().set_accels_for_action(&compound_name, accels);
+        REGISTERED.with(|registered| {
+            registered.borrow_mut().push(RegisteredAction {
+                name: compound_name,
+                accels: accels.iter().map(|&s| s.into()).collect(),
+            })
+        });
     }
 
     /// Registers an action that emits a single message to a given relm4 sender.
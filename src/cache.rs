@@ -0,0 +1,124 @@
+//! Metadata cache: the lists russpot has shown, kept in SQLite in the user cache directory
+//! (usually ~/.cache/russpot/metadata.sqlite), so that they render instantly on the next visit.
+//!
+//! A list is stored whole, as the JSON of its items, under a key derived from its source.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{debug, error};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::spotconn::model::{ListEntry, SpotItem};
+
+pub struct Cache {
+    db: Mutex<Connection>,
+}
+
+/// A list as it was last fetched.
+pub struct CachedList {
+    /// Snapshot of the playlist the items came from, None for other lists.
+    pub snapshot_id: Option<String>,
+    pub items: Vec<ListEntry>,
+}
+
+impl Cache {
+    /// Returns the global cache, or None if it could not be opened.
+    pub fn global() -> Option<&'static Cache> {
+        static CACHE: OnceLock<Option<Cache>> = OnceLock::new();
+        CACHE
+            .get_or_init(|| {
+                let path = cache_path("metadata.sqlite")?;
+                Cache::open(&path)
+                    .map_err(|e| error!("Metadata cache disabled, cannot open {:?}: {}", path, e))
+                    .ok()
+            })
+            .as_ref()
+    }
+
+    fn open(path: &Path) -> Result<Cache, String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let db = Connection::open(path).map_err(|e| e.to_string())?;
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS lists (
+                key TEXT PRIMARY KEY,
+                snapshot_id TEXT,
+                items TEXT NOT NULL,
+                updated_at INTEGER NOT NULL
+            )",
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(Cache { db: Mutex::new(db) })
+    }
+
+    /// Returns the list last stored under the key, if any.
+    pub fn load(&self, key: &str) -> Option<CachedList> {
+        let row = self
+            .db
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT snapshot_id, items FROM lists WHERE key = ?1",
+                params![key],
+                |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional();
+        match row {
+            Ok(Some((snapshot_id, items))) => match serde_json::from_str(&items) {
+                Ok(items) => Some(CachedList { snapshot_id, items }),
+                // Most likely written by an older russpot, it will be replaced once refetched.
+                Err(e) => {
+                    debug!("Ignoring unreadable cache entry {}: {}", key, e);
+                    None
+                }
+            },
+            Ok(None) => None,
+            Err(e) => {
+                error!("Cannot read {} from the cache: {}", key, e);
+                None
+            }
+        }
+    }
+
+    /// Replaces the list stored under the key.
+    pub fn store(&self, key: &str, snapshot_id: Option<&str>, items: &[ListEntry]) {
+        let items = serde_json::to_string(items).expect("items should serialize to JSON");
+        let updated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        let result = self.db.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO lists (key, snapshot_id, items, updated_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![key, snapshot_id, items, updated_at],
+        );
+        if let Err(e) = result {
+            error!("Cannot write {} to the cache: {}", key, e);
+        }
+    }
+}
+
+/// Key under which the contents of the source are cached. None for lists not worth keeping.
+pub fn cache_key(source: &SpotItem) -> Option<String> {
+    match source {
+        SpotItem::UserPlaylists => Some("user:playlists".to_string()),
+        SpotItem::SavedShows => Some("user:shows".to_string()),
+        SpotItem::RecentlyPlayed => Some("user:recent".to_string()),
+        SpotItem::TopTracks(range) => Some(format!("user:top-tracks:{:?}", range)),
+        SpotItem::TopArtists(range) => Some(format!("user:top-artists:{:?}", range)),
+        SpotItem::Playlist(_) | SpotItem::Album(_) | SpotItem::Show(_) | SpotItem::Artist(_) => {
+            source.uri()
+        }
+        SpotItem::SearchResults { st, query } => Some(format!("search:{:?}:{}", st, query)),
+        // Devices come and go, and radio is different every time.
+        SpotItem::Devices | SpotItem::Radio(_) => None,
+        SpotItem::Track(_) | SpotItem::Episode(_) | SpotItem::Device(_) => None,
+    }
+}
+
+/// Path of a file in the russpot cache directory.
+pub fn cache_path(file: &str) -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("russpot").join(file))
+}
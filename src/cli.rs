@@ -0,0 +1,169 @@
+//! Command line client, for scripting russpot from shell pipelines.
+//!
+//! Lists print one item per line: the uri, name and artist separated by tabs,
+//! or, with --json, a JSON object per line. Devices print their id, name, type,
+//! volume and "active" for the active one instead.
+
+use std::io::{self, Write};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use log::error;
+use rspotify::model::{AlbumId, ArtistId, Offset, PlaylistId, SearchType, ShowId};
+use rspotify::prelude::*;
+
+use russpot::spotconn::model::{PlayTarget, SpotItem};
+use russpot::spotconn::SpotConn;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "russpot-cli",
+    about = "Search, browse and play Spotify from the shell"
+)]
+struct Args {
+    /// Print items as JSON objects, one per line.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Search the Spotify catalog.
+    Search {
+        #[arg(value_enum)]
+        kind: Kind,
+        #[arg(required = true)]
+        query: Vec<String>,
+    },
+    /// List the playlists of the current user.
+    Playlists,
+    /// List the contents of a playlist, album, show or artist.
+    Tracks { uri: String },
+    /// Start playback on the preferred device, or on the active one.
+    Play {
+        uri: String,
+        /// Uri of the track to start from, within the played context.
+        #[arg(long)]
+        offset: Option<String>,
+    },
+    /// List the Spotify Connect devices.
+    Devices,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Kind {
+    Track,
+    Album,
+    Artist,
+    Playlist,
+    Show,
+}
+
+impl From<Kind> for SearchType {
+    fn from(kind: Kind) -> Self {
+        match kind {
+            Kind::Track => SearchType::Track,
+            Kind::Album => SearchType::Album,
+            Kind::Artist => SearchType::Artist,
+            Kind::Playlist => SearchType::Playlist,
+            Kind::Show => SearchType::Show,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    let args = Args::parse();
+    let json = args.json;
+    let print = move |item: SpotItem| print_item(&item, json);
+    let spot = SpotConn::global_without_player();
+    let listed = match args.command {
+        Command::Search { kind, query } => spot.search(kind.into(), query.join(" "), print).await,
+        Command::Playlists => {
+            spot.current_user_playlists(|sp| print(SpotItem::Playlist(sp)))
+                .await
+        }
+        Command::Tracks { uri } => {
+            if PlaylistId::from_uri(&uri).is_ok() {
+                spot.playlist_items(uri, |entry| print(entry.item)).await
+            } else if AlbumId::from_uri(&uri).is_ok() {
+                spot.album_tracks(uri, |ft| print(SpotItem::Track(ft)))
+                    .await
+            } else if ShowId::from_uri(&uri).is_ok() {
+                spot.show_episodes(uri, |ep| print(SpotItem::Episode(ep)))
+                    .await
+            } else if ArtistId::from_uri(&uri).is_ok() {
+                spot.artist_albums(uri, |album| print(SpotItem::Album(album)))
+                    .await
+            } else {
+                error!("{} has no tracks to list", uri);
+                std::process::exit(2);
+            }
+        }
+        Command::Play { uri, offset } => {
+            let Some(target) = PlayTarget::from_uri(&uri, offset.map(Offset::Uri)) else {
+                error!("{} is not playable", uri);
+                std::process::exit(2);
+            };
+            spot.play_context(target).await
+        }
+        Command::Devices => spot.devices().await.map(|devices| {
+            devices
+                .into_iter()
+                .for_each(|dev| print(SpotItem::Device(dev)))
+        }),
+    };
+    if let Err(e) = listed {
+        error!("Request failed: {:?}", e);
+        std::process::exit(1);
+    }
+}
+
+fn print_item(item: &SpotItem, json: bool) {
+    // Devices have no uri, their id is what the Web API takes instead.
+    let uri = match item {
+        SpotItem::Device(dev) => dev.id.clone(),
+        other => other.uri(),
+    };
+    if json {
+        let mut value = item.summary();
+        value["uri"] = uri.into();
+        if let SpotItem::Device(dev) = item {
+            value["type"] = format!("{:?}", dev._type).into();
+            value["volume"] = dev.volume_percent.into();
+            value["active"] = dev.is_active.into();
+        }
+        write_line(format_args!("{}", value));
+    } else if let SpotItem::Device(dev) = item {
+        write_line(format_args!(
+            "{}\t{}\t{:?}\t{}\t{}",
+            uri.unwrap_or_default(),
+            dev.name,
+            dev._type,
+            dev.volume_percent
+                .map(|percent| percent.to_string())
+                .unwrap_or_default(),
+            if dev.is_active { "active" } else { "" }
+        ));
+    } else {
+        write_line(format_args!(
+            "{}\t{}\t{}",
+            uri.unwrap_or_default(),
+            item.name(),
+            item.artist()
+        ));
+    }
+}
+
+/// Writes a line to stdout. Once the reader is gone, as with `| head`, exits quietly.
+fn write_line(line: std::fmt::Arguments) {
+    if let Err(e) = writeln!(io::stdout().lock(), "{}", line) {
+        if e.kind() == io::ErrorKind::BrokenPipe {
+            std::process::exit(0);
+        }
+        error!("Cannot write the output: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -1,6 +1,12 @@
 pub mod actions;
 pub mod denselist;
+pub mod listheader;
+pub mod listorder;
 pub mod multiview;
+pub mod palette;
+pub mod playlistdialog;
 pub mod searchpage;
 pub mod smallblock;
 pub mod switchview;
+pub mod toppage;
+pub mod trackdetails;
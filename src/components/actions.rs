@@ -4,19 +4,47 @@
 use gtk::prelude::*;
 use relm4::{prelude::*, SimpleComponent};
 
+use super::playlistdialog::PlaylistOp;
+
 #[derive(Debug)]
 pub struct Actions {}
 
+/// Ways of sharing the selected items, or the item under cursor.
+#[derive(Debug, Clone, Copy)]
+pub enum ShareOp {
+    /// Copy the spotify: URI.
+    CopyUri,
+    /// Copy the open.spotify.com link.
+    CopyLink,
+    /// Copy "Artist – Title".
+    CopyLine,
+    OpenInBrowser,
+}
+
 #[derive(Debug)]
 pub enum ActionsOutput {
     PlayNow,
     SpircNow,
+    Radio,
+    TransferPlayback,
+    PreferDevice,
+    Playlist(PlaylistOp),
+    Share(ShareOp),
+    Enqueue,
+    Like,
 }
 
 #[derive(Debug)]
 pub enum ActionsInput {
     ClickedPlay,
     ClickedSpirc,
+    ClickedRadio,
+    ClickedTransfer,
+    ClickedPreferDevice,
+    ClickedPlaylist(PlaylistOp),
+    ClickedShare(ShareOp),
+    ClickedEnqueue,
+    ClickedLike,
 }
 
 #[relm4::component(pub)]
@@ -39,6 +67,90 @@ impl SimpleComponent for Actions {
                 set_label: "Spirc",
                 connect_clicked => ActionsInput::ClickedSpirc,
             },
+            gtk::Button {
+                set_label: "Radio",
+                connect_clicked => ActionsInput::ClickedRadio,
+            },
+            gtk::Label {
+                set_label: "Devices",
+                set_xalign: 0.0,
+            },
+            gtk::Button {
+                set_label: "Transfer playback here",
+                connect_clicked => ActionsInput::ClickedTransfer,
+            },
+            gtk::Button {
+                set_label: "Play now on this device",
+                connect_clicked => ActionsInput::ClickedPreferDevice,
+            },
+            gtk::Label {
+                set_label: "Playlists",
+                set_xalign: 0.0,
+            },
+            gtk::Button {
+                set_label: "New playlist",
+                connect_clicked => ActionsInput::ClickedPlaylist(PlaylistOp::Create),
+            },
+            gtk::Button {
+                set_label: "Save list as playlist",
+                connect_clicked => ActionsInput::ClickedPlaylist(PlaylistOp::CreateFromList),
+            },
+            gtk::Button {
+                set_label: "Rename",
+                connect_clicked => ActionsInput::ClickedPlaylist(PlaylistOp::Rename),
+            },
+            gtk::Button {
+                set_label: "Edit description",
+                connect_clicked => ActionsInput::ClickedPlaylist(PlaylistOp::Describe),
+            },
+            gtk::Button {
+                set_label: "Public / collaborative",
+                connect_clicked => ActionsInput::ClickedPlaylist(PlaylistOp::Visibility),
+            },
+            gtk::Button {
+                set_label: "Delete playlist",
+                connect_clicked => ActionsInput::ClickedPlaylist(PlaylistOp::Delete),
+            },
+            gtk::Label {
+                set_label: "Selected tracks",
+                set_xalign: 0.0,
+            },
+            gtk::Button {
+                set_label: "Add to queue",
+                connect_clicked => ActionsInput::ClickedEnqueue,
+            },
+            gtk::Button {
+                set_label: "Like",
+                connect_clicked => ActionsInput::ClickedLike,
+            },
+            gtk::Button {
+                set_label: "Add to playlist",
+                connect_clicked => ActionsInput::ClickedPlaylist(PlaylistOp::AddItems),
+            },
+            gtk::Button {
+                set_label: "Remove from this playlist",
+                connect_clicked => ActionsInput::ClickedPlaylist(PlaylistOp::RemoveItems),
+            },
+            gtk::Label {
+                set_label: "Share",
+                set_xalign: 0.0,
+            },
+            gtk::Button {
+                set_label: "Copy URI",
+                connect_clicked => ActionsInput::ClickedShare(ShareOp::CopyUri),
+            },
+            gtk::Button {
+                set_label: "Copy link",
+                connect_clicked => ActionsInput::ClickedShare(ShareOp::CopyLink),
+            },
+            gtk::Button {
+                set_label: "Copy artist and title",
+                connect_clicked => ActionsInput::ClickedShare(ShareOp::CopyLine),
+            },
+            gtk::Button {
+                set_label: "Open in browser",
+                connect_clicked => ActionsInput::ClickedShare(ShareOp::OpenInBrowser),
+            },
         },
     }
 
@@ -56,6 +168,19 @@ impl SimpleComponent for Actions {
         match message {
             ActionsInput::ClickedPlay => sender.output_sender().emit(ActionsOutput::PlayNow),
             ActionsInput::ClickedSpirc => sender.output_sender().emit(ActionsOutput::SpircNow),
+            ActionsInput::ClickedRadio => sender.output_sender().emit(ActionsOutput::Radio),
+            ActionsInput::ClickedTransfer => {
+                sender.output_sender().emit(ActionsOutput::TransferPlayback)
+            }
+            ActionsInput::ClickedPreferDevice => {
+                sender.output_sender().emit(ActionsOutput::PreferDevice)
+            }
+            ActionsInput::ClickedPlaylist(op) => {
+                sender.output_sender().emit(ActionsOutput::Playlist(op))
+            }
+            ActionsInput::ClickedShare(op) => sender.output_sender().emit(ActionsOutput::Share(op)),
+            ActionsInput::ClickedEnqueue => sender.output_sender().emit(ActionsOutput::Enqueue),
+            ActionsInput::ClickedLike => sender.output_sender().emit(ActionsOutput::Like),
         }
     }
 }
@@ -1,18 +1,32 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
 use gtk::prelude::*;
 use log::{debug, error, warn};
 use relm4::factory::FactoryVecDeque;
 use relm4::prelude::*;
-use rspotify::{model::Offset, prelude::*};
+use rspotify::{
+    model::{Offset, PlayContextId, SearchType},
+    prelude::*,
+    ClientResult,
+};
 
 use crate::{
+    cache::{cache_key, Cache},
     navigation::{NavCommand, NavOutput},
+    offline::Offline,
+    settings::{Column, Settings},
     spotconn::{
-        model::{format_search_type, SpotItem},
+        model::{
+            format_search_type, offset_at, LibraryChange, ListEntry, PlaySource, PlayTarget,
+            SpotItem,
+        },
         SpotConn,
     },
 };
 
-use super::smallblock;
+use super::listorder::{ListOrder, SortCommand};
+use super::{listheader, smallblock};
 
 #[derive(Debug, Clone)]
 pub struct Init {
@@ -22,8 +36,27 @@ pub struct Init {
 #[derive(Debug)]
 pub struct Model {
     init: Init,
+    /// Cover, description and totals of a playlist or album. Hidden for other lists.
+    header: Controller<listheader::Model>,
     dense_items: FactoryVecDeque<ChildItem>,
+    /// Everything loaded, in the order Spotify gave it.
+    entries: Vec<ListEntry>,
+    /// Indices into entries, in display order.
+    shown: Vec<usize>,
+    order: ListOrder,
+    /// Indices into entries of the selected items.
+    selected: HashSet<usize>,
+    /// In visual mode: the display position it started at, and what was selected before.
+    visual: Option<(usize, HashSet<usize>)>,
+    /// Details shown next to each item, chosen per list kind.
+    columns: Vec<Column>,
+    /// Check buttons for the columns, in the popover of the Columns button.
+    columns_menu: gtk::Box,
     cursor: Option<DynamicIndex>,
+    /// Whether Spotify is unreachable, so that the list is only what was cached.
+    offline: bool,
+    /// Uri of the item to put the cursor on, once it is loaded.
+    pending_cursor: Option<String>,
 }
 
 impl Model {
@@ -40,8 +73,8 @@ impl Model {
     pub fn descend(&self) -> Option<Init> {
         debug!("Attempting a descent into {:?}", self.item_under_cursor());
         match self.item_under_cursor()? {
-            // Tracks cannot be descended into, anything else can:
-            SpotItem::Track(_) => None,
+            // Tracks, episodes and devices cannot be descended into, anything else can:
+            SpotItem::Track(_) | SpotItem::Episode(_) | SpotItem::Device(_) => None,
             item => Some(Init {
                 source: item.clone(),
             }),
@@ -49,7 +82,7 @@ impl Model {
     }
 
     // Reutrns an rspotify PlayContext for the collection, including the cursor position.
-    pub fn play_context(&self) -> Option<(PlayContextId<'static>, Option<Offset>)> {
+    pub fn play_context(&self) -> Option<PlayTarget> {
         let item = self.item_under_cursor();
         match (self.init.source.context_id(), item) {
             (None, None) => {
@@ -59,13 +92,55 @@ impl Model {
                 );
                 None
             }
+            // Lists of tracks with no context of their own play as a list of uris.
+            (None, Some(item))
+                if matches!(
+                    self.init.source,
+                    SpotItem::Radio(_) | SpotItem::RecentlyPlayed | SpotItem::TopTracks(_)
+                ) =>
+            {
+                debug!("play_context: listed tracks, starting at {:?}", item);
+                // In the original order, whatever the list is sorted by. Unplayable tracks
+                // are left out, so the cursor on one starts at the next playable one.
+                let start = self.cursor_position().map_or(0, |pos| self.shown[pos]);
+                let playable = |entry: &ListEntry| {
+                    Some(entry)
+                        .filter(|entry| entry.item.is_playable())
+                        .and_then(|entry| entry.item.playable_id())
+                };
+                let ids: Vec<_> = self.entries.iter().filter_map(playable).collect();
+                // By index among the ids sent, as the same track may be listed twice.
+                let before = self.entries[..start].iter().filter_map(playable).count();
+                let offset = (before < ids.len()).then(|| offset_at(before));
+                Some(PlayTarget {
+                    source: PlaySource::Tracks(ids),
+                    offset,
+                    position: None,
+                })
+            }
+            (None, Some(SpotItem::Episode(ep))) => {
+                debug!(
+                    "play_context: episode in its show, because list is {:?}",
+                    self.init.source
+                );
+                let item = SpotItem::Episode(ep.clone());
+                Some(PlayTarget {
+                    source: PlaySource::Context(PlayContextId::Show(ep.show.id)),
+                    offset: item.uri().map(Offset::Uri),
+                    position: item.resume_position(),
+                })
+            }
             (None, Some(item)) => match item.context_id() {
                 Some(ctx) => {
                     debug!(
                         "play_context: for item, because list is {:?}",
                         self.init.source
                     );
-                    Some((ctx.clone_static(), None))
+                    Some(PlayTarget {
+                        source: PlaySource::Context(ctx.clone_static()),
+                        offset: None,
+                        position: None,
+                    })
                 }
                 None => {
                     debug!("play_context: None, because item is {:?}", item);
@@ -74,20 +149,53 @@ impl Model {
             },
             (Some(ctx), item) => {
                 debug!("Play context for list {:?}", self.init.source);
-                Some((
-                    ctx.clone_static(),
-                    item.and_then(|it| it.uri()).map(Offset::Uri),
-                ))
+                // In playlists and albums, the index in the original order finds the right copy
+                // of a track listed twice. Other contexts list relinked tracks under their
+                // original uri.
+                let offset = match ctx {
+                    PlayContextId::Playlist(_) | PlayContextId::Album(_) => {
+                        self.cursor_position().map(|pos| offset_at(self.shown[pos]))
+                    }
+                    _ => item
+                        .as_ref()
+                        .and_then(|it| it.original_uri())
+                        .map(Offset::Uri),
+                };
+                Some(PlayTarget {
+                    source: PlaySource::Context(ctx.clone_static()),
+                    position: item.as_ref().and_then(SpotItem::resume_position),
+                    offset,
+                })
             }
         }
     }
 }
 
+/// Selecting several items, for actions to apply to all of them.
+#[derive(Debug, Clone, Copy)]
+pub enum SelectCommand {
+    /// Start or end visual mode, in which moving the cursor extends the selection.
+    Visual,
+    /// Select or unselect the item under cursor.
+    Toggle,
+    Clear,
+}
+
 #[derive(Debug)]
 pub enum In {
     Nav(NavCommand),
     MoveCursorTo(DynamicIndex),
+    /// Put the cursor on the item with this uri, now or as soon as it loads.
+    RestoreCursor(String),
     Reset(SpotItem),
+    Library(LibraryChange),
+    /// Build the rows of a device list again, to show which device is preferred now.
+    RefreshDevices,
+    /// Change the order the items are shown in. Playing still goes in the original order.
+    Sort(SortCommand),
+    Select(SelectCommand),
+    #[doc(hidden)]
+    ToggleColumn(Column),
 }
 
 #[derive(Debug)]
@@ -97,7 +205,10 @@ pub enum Out {
 
 #[derive(Debug)]
 pub enum CmdOut {
-    AddItem(SpotItem),
+    AddItem(ListEntry),
+    /// A fresh copy of the whole list.
+    ReplaceAll(Vec<ListEntry>),
+    Online(bool),
 }
 
 #[relm4::factory(pub)]
@@ -115,8 +226,42 @@ impl FactoryComponent for Model {
             set_hexpand: true,
             set_vexpand: true,
 
+            self.header.widget() -> &gtk::Box {},
+            gtk::Box::new(gtk::Orientation::Horizontal, 0) {
+                gtk::Label {
+                    set_hexpand: true,
+                    set_label: &self.list_title(),
+                },
+                gtk::Label {
+                    set_css_classes: &["order"],
+                    #[watch]
+                    set_label: &self.selection_summary(),
+                    #[watch]
+                    set_visible: !self.selected.is_empty() || self.visual.is_some(),
+                },
+                gtk::Label {
+                    set_css_classes: &["order"],
+                    #[watch]
+                    set_label: &self.order.describe(),
+                    #[watch]
+                    set_visible: !self.order.is_original(),
+                },
+                gtk::MenuButton {
+                    set_label: "Columns",
+                    set_has_frame: false,
+                    #[watch]
+                    set_visible: Model::column_kind(&self.init.source).is_some(),
+                    #[wrap(Some)]
+                    set_popover = &gtk::Popover {
+                        set_child: Some(&self.columns_menu),
+                    },
+                },
+            },
             gtk::Label {
-                set_label: &self.list_title(),
+                set_css_classes: &["offline"],
+                set_label: "Offline: showing what was cached",
+                #[watch]
+                set_visible: self.offline,
             },
 
             self.dense_items.widget() -> &gtk::Box {
@@ -139,18 +284,52 @@ impl FactoryComponent for Model {
                 ChildOut::Clicked(idx) => In::MoveCursorTo(idx),
             });
 
+        let header = listheader::Model::builder()
+            .launch(init.source.clone())
+            .detach();
+        let columns = Model::column_kind(&init.source)
+            .map(|kind| Settings::get().columns(kind))
+            .unwrap_or_default();
         let model = Model {
             init,
+            header,
             dense_items,
+            entries: vec![],
+            shown: vec![],
+            order: ListOrder::default(),
+            selected: HashSet::new(),
+            visual: None,
+            columns,
+            columns_menu: gtk::Box::new(gtk::Orientation::Vertical, 0),
             cursor: None,
+            offline: false,
+            pending_cursor: None,
         };
 
+        model.build_columns_menu(&sender);
         Model::init_data_loading(&model.init.source, &sender);
+        sender.command(|out, shutdown| {
+            shutdown
+                .register(async move {
+                    let mut online = Offline::global().subscribe();
+                    loop {
+                        out.emit(CmdOut::Online(*online.borrow_and_update()));
+                        if online.changed().await.is_err() {
+                            break;
+                        }
+                    }
+                })
+                .drop_on_shutdown()
+        });
 
         model
     }
 
     fn update(&mut self, msg: Self::Input, sender: FactorySender<Self>) {
+        if let In::Nav(_) = msg {
+            // The user took over, don't yank the cursor away once the item loads.
+            self.pending_cursor = None;
+        }
         match msg {
             In::Nav(NavCommand::ClearCursor) => {
                 let mut items = self.dense_items.guard();
@@ -164,10 +343,82 @@ impl FactoryComponent for Model {
                 self.cursor = None;
             }
             In::Reset(source) => {
+                self.header.emit(listheader::In::Reset(source.clone()));
+                self.columns = Model::column_kind(&source)
+                    .map(|kind| Settings::get().columns(kind))
+                    .unwrap_or_default();
                 self.init.source = source;
+                self.build_columns_menu(&sender);
+                self.order = ListOrder::default();
+                self.selected.clear();
+                self.visual = None;
+                self.entries.clear();
+                self.shown.clear();
+                self.cursor = None;
                 self.dense_items.guard().clear();
+                self.items_changed();
                 Model::init_data_loading(&self.init.source, &sender);
             }
+            In::Library(change) => {
+                // Items added to or removed from the playlist on display show once refetched.
+                if let (SpotItem::Playlist(current), LibraryChange::PlaylistUpdated(sp)) =
+                    (&self.init.source, &change)
+                {
+                    if current.id == sp.id && current.snapshot_id != sp.snapshot_id {
+                        self.init.source = SpotItem::Playlist(sp.clone());
+                        Model::init_data_loading(&self.init.source, &sender);
+                    }
+                }
+                self.apply_library_change(change);
+                self.items_changed();
+            }
+            In::RefreshDevices if matches!(self.init.source, SpotItem::Devices) => {
+                // Devices have no uri to find the cursor by, but the order stays the same.
+                let pos = self.cursor_position();
+                self.show_entries();
+                self.items_changed();
+                if let Some(child) = pos.and_then(|pos| self.dense_items.get(pos)) {
+                    sender.input(In::MoveCursorTo(child.self_idx.clone()));
+                }
+            }
+            In::RefreshDevices => {}
+            In::RestoreCursor(uri) => {
+                self.pending_cursor = Some(uri);
+                self.restore_cursor(&sender);
+            }
+            In::Sort(cmd) => {
+                self.order.apply(cmd);
+                self.show_entries();
+                self.items_changed();
+                // Bring the item under cursor back into view, wherever it went.
+                if let Some(cursor) = self.cursor.clone() {
+                    sender.input(In::MoveCursorTo(cursor));
+                }
+            }
+            In::Select(SelectCommand::Visual) => {
+                // Leaving visual mode keeps what it selected.
+                if self.visual.take().is_none() {
+                    if let Some(pos) = self.cursor_position() {
+                        self.visual = Some((pos, self.selected.clone()));
+                        self.extend_visual();
+                    }
+                }
+            }
+            In::Select(SelectCommand::Toggle) => {
+                self.visual = None;
+                if let Some(idx) = self.cursor_position().map(|pos| self.shown[pos]) {
+                    let mut selected = self.selected.clone();
+                    if !selected.remove(&idx) {
+                        selected.insert(idx);
+                    }
+                    self.set_selected(selected);
+                }
+            }
+            In::Select(SelectCommand::Clear) => {
+                self.visual = None;
+                self.set_selected(HashSet::new());
+            }
+            In::ToggleColumn(column) => self.toggle_column(column),
             In::MoveCursorTo(dyn_idx) => {
                 let mut items = self.dense_items.guard();
                 let mut move_focus_to: Option<gtk::Button> = None;
@@ -191,6 +442,10 @@ impl FactoryComponent for Model {
                     }
                     None => error!("cannot set cursor, message back up?"),
                 }
+                drop(items);
+                if self.visual.is_some() {
+                    self.extend_visual();
+                }
             }
             // TODO: distinguish between directions
             In::Nav(NavCommand::Up) => self.move_cursor(-1, &sender),
@@ -201,8 +456,32 @@ impl FactoryComponent for Model {
     }
     fn update_cmd(&mut self, message: Self::CommandOutput, sender: FactorySender<Self>) {
         match message {
-            CmdOut::AddItem(item) => {
-                self.dense_items.guard().push_back(item);
+            CmdOut::AddItem(entry) => {
+                self.entries.push(entry.clone());
+                let idx = self.entries.len() - 1;
+                let pos = self.order.insertion_point(&self.entries, &self.shown, idx);
+                self.shown.insert(pos, idx);
+                let columns = self.columns.clone();
+                self.dense_items
+                    .guard()
+                    .insert(pos, smallblock::Init { entry, columns });
+                self.items_changed();
+                self.restore_cursor(&sender);
+            }
+            CmdOut::ReplaceAll(entries) => {
+                // The indices are of the old entries.
+                self.selected.clear();
+                self.entries = entries;
+                self.show_entries();
+                self.items_changed();
+                self.restore_cursor(&sender);
+            }
+            CmdOut::Online(online) => {
+                let reconnected = online && self.offline;
+                self.offline = !online;
+                if reconnected {
+                    Model::init_data_loading(&self.init.source, &sender);
+                }
             }
         }
     }
@@ -214,6 +493,9 @@ impl Model {
             SpotItem::SearchResults { ref st, ref query } => {
                 format!("{} matching {}", format_search_type(st), query)
             }
+            SpotItem::TopTracks(_) | SpotItem::TopArtists(_) | SpotItem::Radio(_) => {
+                self.init.source.name()
+            }
             _ => "".to_string(),
         }
     }
@@ -223,6 +505,292 @@ impl Model {
         Some(item.sb.model().get_content().clone())
     }
 
+    /// The collection displayed in this list.
+    pub fn source(&self) -> &SpotItem {
+        &self.init.source
+    }
+
+    /// All the items loaded so far, in display order.
+    pub fn items(&self) -> Vec<SpotItem> {
+        self.dense_items
+            .iter()
+            .map(|child| child.sb.model().get_content().clone())
+            .collect()
+    }
+
+    fn position_of(&self, uri: &str) -> Option<usize> {
+        self.dense_items
+            .iter()
+            .position(|child| child.sb.model().get_content().uri().as_deref() == Some(uri))
+    }
+
+    /// Shows the entries anew in the current order, keeping the cursor on the same item if it is still there.
+    fn show_entries(&mut self) {
+        let cursor_uri = self.current_item().and_then(|item| item.uri());
+        self.cursor = None;
+        self.visual = None;
+        self.shown = self.order.sorted(&self.entries);
+        {
+            let mut guard = self.dense_items.guard();
+            guard.clear();
+            for idx in &self.shown {
+                guard.push_back(smallblock::Init {
+                    entry: self.entries[*idx].clone(),
+                    columns: self.columns.clone(),
+                });
+            }
+        }
+        self.set_selected(std::mem::take(&mut self.selected));
+        let Some(idx) = cursor_uri.and_then(|uri| self.position_of(&uri)) else {
+            return;
+        };
+        let mut guard = self.dense_items.guard();
+        let child = guard.get_mut(idx).unwrap();
+        child.has_cursor = true;
+        self.cursor = Some(child.self_idx.clone());
+    }
+
+    /// The selected items in display order, or the item under cursor if nothing is selected.
+    pub fn selection(&self) -> Vec<SpotItem> {
+        if self.selected.is_empty() {
+            return self.current_item().into_iter().collect();
+        }
+        self.shown
+            .iter()
+            .filter(|idx| self.selected.contains(idx))
+            .map(|idx| self.entries[*idx].item.clone())
+            .collect()
+    }
+
+    fn selection_summary(&self) -> String {
+        let count = format!("{} selected", self.selected.len());
+        match self.visual {
+            Some(_) => format!("VISUAL, {}", count),
+            None => count,
+        }
+    }
+
+    fn cursor_position(&self) -> Option<usize> {
+        self.cursor.as_ref().map(DynamicIndex::current_index)
+    }
+
+    fn set_selected(&mut self, selected: HashSet<usize>) {
+        let mut items = self.dense_items.guard();
+        for (pos, idx) in self.shown.iter().enumerate() {
+            let is_selected = selected.contains(idx);
+            // get_mut re-renders the item, so only touch the ones which change.
+            if items.get(pos).unwrap().selected != is_selected {
+                items.get_mut(pos).unwrap().selected = is_selected;
+            }
+        }
+        self.selected = selected;
+    }
+
+    /// Selects everything between where visual mode started and the cursor,
+    /// on top of what was selected before.
+    fn extend_visual(&mut self) {
+        let (Some((start, before)), Some(pos)) = (&self.visual, self.cursor_position()) else {
+            return;
+        };
+        let (from, to) = (pos.min(*start), pos.max(*start));
+        if to >= self.shown.len() {
+            return;
+        }
+        let mut selected = before.clone();
+        selected.extend(&self.shown[from..=to]);
+        self.set_selected(selected);
+    }
+
+    /// Which column settings apply to the list. None for lists of anything but tracks and episodes.
+    fn column_kind(source: &SpotItem) -> Option<&'static str> {
+        match source {
+            SpotItem::Playlist(_) => Some("playlist"),
+            SpotItem::Album(_) => Some("album"),
+            SpotItem::Show(_) => Some("show"),
+            SpotItem::RecentlyPlayed => Some("recent"),
+            SpotItem::TopTracks(_) => Some("top"),
+            SpotItem::Radio(_) => Some("radio"),
+            SpotItem::SearchResults {
+                st: SearchType::Track | SearchType::Episode,
+                ..
+            } => Some("search"),
+            _ => None,
+        }
+    }
+
+    fn build_columns_menu(&self, sender: &FactorySender<Self>) {
+        while let Some(child) = self.columns_menu.first_child() {
+            self.columns_menu.remove(&child);
+        }
+        for column in Column::ALL {
+            let check = gtk::CheckButton::with_label(column.title());
+            check.set_active(self.columns.contains(&column));
+            let input = sender.input_sender().clone();
+            check.connect_toggled(move |_| input.emit(In::ToggleColumn(column)));
+            self.columns_menu.append(&check);
+        }
+    }
+
+    /// Shows or hides the column in this list, and in all the lists of the kind from now on.
+    fn toggle_column(&mut self, column: Column) {
+        let Some(kind) = Model::column_kind(&self.init.source) else {
+            return;
+        };
+        let shown = !self.columns.contains(&column);
+        self.columns = Column::ALL
+            .into_iter()
+            .filter(|c| {
+                if *c == column {
+                    shown
+                } else {
+                    self.columns.contains(c)
+                }
+            })
+            .collect();
+        let columns = self.columns.clone();
+        Settings::update(|settings| {
+            settings.columns.insert(kind.to_string(), columns);
+        });
+        for child in self.dense_items.iter() {
+            child
+                .sb
+                .emit(smallblock::In::SetColumns(self.columns.clone()));
+        }
+    }
+
+    /// Updates the header totals, and the group or disc headings.
+    fn items_changed(&mut self) {
+        if matches!(self.init.source, SpotItem::Playlist(_) | SpotItem::Album(_)) {
+            let (count, duration) = self.entries.iter().fold(
+                (0, chrono::Duration::zero()),
+                |(count, duration), entry| {
+                    let length = entry.item.duration();
+                    (
+                        count + 1,
+                        duration + length.unwrap_or_else(chrono::Duration::zero),
+                    )
+                },
+            );
+            self.header.emit(listheader::In::Items { count, duration });
+        }
+
+        // A heading above the first item of each group, like the artist name,
+        // or "Disc 2" on albums with several discs shown in their own order.
+        let groups: Vec<Option<String>> = if self.order.is_grouped() {
+            self.shown
+                .iter()
+                .map(|idx| self.order.group_of(&self.entries[*idx]))
+                .collect()
+        } else if matches!(self.init.source, SpotItem::Album(_)) && self.order.is_original() {
+            let discs: Vec<Option<i32>> = self
+                .entries
+                .iter()
+                .map(|entry| match &entry.item {
+                    SpotItem::Track(ft) => Some(ft.disc_number),
+                    _ => None,
+                })
+                .collect();
+            let several = discs.windows(2).any(|pair| pair[0] != pair[1]);
+            discs
+                .iter()
+                .map(|disc| {
+                    disc.filter(|_| several)
+                        .map(|disc| format!("Disc {}", disc))
+                })
+                .collect()
+        } else {
+            vec![None; self.shown.len()]
+        };
+        let mut items = self.dense_items.guard();
+        for (idx, group) in groups.iter().enumerate() {
+            let starts_group = idx == 0 || groups[idx - 1] != *group;
+            let heading = group.clone().filter(|_| starts_group);
+            // get_mut re-renders the item, so only touch the ones which change.
+            if items.get(idx).unwrap().heading != heading {
+                items.get_mut(idx).unwrap().heading = heading;
+            }
+        }
+    }
+
+    /// Moves the cursor to the item requested with RestoreCursor, if it is loaded by now.
+    fn restore_cursor(&mut self, sender: &FactorySender<Self>) {
+        let Some(idx) = self
+            .pending_cursor
+            .as_deref()
+            .and_then(|uri| self.position_of(uri))
+        else {
+            return;
+        };
+        self.pending_cursor = None;
+        let child = self.dense_items.get(idx).unwrap();
+        sender.input(In::MoveCursorTo(child.self_idx.clone()));
+    }
+
+    /// Updates the saved playlists in place. Lists of anything else are not affected.
+    fn apply_library_change(&mut self, change: LibraryChange) {
+        if !matches!(self.init.source, SpotItem::UserPlaylists) {
+            return;
+        }
+        // The entries are about to move.
+        self.visual = None;
+        self.set_selected(HashSet::new());
+        match &change {
+            LibraryChange::PlaylistCreated(sp) => {
+                self.entries
+                    .insert(0, SpotItem::Playlist(sp.clone()).into());
+            }
+            LibraryChange::PlaylistUpdated(sp) => {
+                let uri = sp.id.uri();
+                if let Some(entry) = self
+                    .entries
+                    .iter_mut()
+                    .find(|entry| entry.item.uri().as_deref() == Some(&uri))
+                {
+                    *entry = SpotItem::Playlist(sp.clone()).into();
+                }
+            }
+            LibraryChange::PlaylistRemoved(uri) => {
+                self.entries
+                    .retain(|entry| entry.item.uri().as_deref() != Some(uri));
+            }
+        }
+        if !self.order.is_original() {
+            // The playlist may well move elsewhere, simply show the whole list again.
+            self.show_entries();
+            return;
+        }
+        self.shown = (0..self.entries.len()).collect();
+        let cursor_idx = self.cursor.as_ref().map(DynamicIndex::current_index);
+        match change {
+            LibraryChange::PlaylistCreated(sp) => {
+                self.dense_items
+                    .guard()
+                    .push_front(SpotItem::Playlist(sp).into());
+            }
+            LibraryChange::PlaylistUpdated(sp) => {
+                let Some(idx) = self.position_of(&sp.id.uri()) else {
+                    return;
+                };
+                let mut items = self.dense_items.guard();
+                items.remove(idx);
+                let new_idx = items.insert(idx, SpotItem::Playlist(sp).into());
+                if cursor_idx == Some(idx) {
+                    items.get_mut(idx).unwrap().has_cursor = true;
+                    self.cursor = Some(new_idx);
+                }
+            }
+            LibraryChange::PlaylistRemoved(uri) => {
+                let Some(idx) = self.position_of(&uri) else {
+                    return;
+                };
+                if cursor_idx == Some(idx) {
+                    self.cursor = None;
+                }
+                self.dense_items.guard().remove(idx);
+            }
+        }
+    }
+
     pub fn current_widget(&self) -> Option<gtk::Widget> {
         self.dense_items
             .get(self.cursor.clone()?.current_index())
@@ -231,42 +799,158 @@ impl Model {
 
     fn init_data_loading(source: &SpotItem, sender: &FactorySender<Model>) {
         debug!("Initializing data load for source {:?}", source);
-        match source.clone() {
-            SpotItem::UserPlaylists => sender.command(move |out, shutdown| {
-                SpotConn::global().current_user_playlists_until_shutdown(shutdown, move |sp| {
-                    out.emit(CmdOut::AddItem(SpotItem::Playlist(sp)))
-                })
-            }),
-
-            SpotItem::Playlist(sp) => sender.command(move |out, shutdown| {
-                SpotConn::global().tracks_in_playlist(shutdown, sp.id.uri(), move |ft| {
-                    out.emit(CmdOut::AddItem(SpotItem::Track(ft)))
-                })
-            }),
+        match source {
+            SpotItem::Device(_) => {
+                panic!("a single device should never be rendered as a list");
+            }
             SpotItem::Track(_) => {
                 panic!("a single track should never be rendered as a list");
             }
-            SpotItem::Album(a) => match source.uri() {
-                Some(uri) => sender.command(move |out, shutdown| {
-                    SpotConn::global().tracks_in_album(shutdown, uri, move |ft| {
-                        out.emit(CmdOut::AddItem(SpotItem::Track(ft)))
-                    })
-                }),
-                None => {
-                    error!(
-                        "Cannot fetch tracks for album {}, as it does not have an URI.",
-                        a.name
-                    );
+            SpotItem::Episode(_) => {
+                panic!("a single episode should never be rendered as a list");
+            }
+            SpotItem::Album(a) if a.id.is_none() => {
+                error!(
+                    "Cannot fetch tracks for album {}, as it does not have an URI.",
+                    a.name
+                );
+                return;
+            }
+            _ => {}
+        }
+        let source = source.clone();
+        // The whole load is dropped on shutdown, so that a half-fetched list never gets cached.
+        sender.command(move |out, shutdown| {
+            shutdown
+                .register(Model::load(source, out))
+                .drop_on_shutdown()
+        });
+    }
+
+    /// Shows the cached contents of the source right away, then fetches them again,
+    /// and swaps them in if anything changed. Playlists with the cached snapshot are not refetched,
+    /// as long as that snapshot was seen live, and nothing is while offline.
+    /// Without a cache the items show as they come, and the fetched list replaces them at the end,
+    /// so that a reload while the list is shown, or still loading, does not add them twice.
+    async fn load(source: SpotItem, out: relm4::Sender<CmdOut>) {
+        let cache = Cache::global();
+        let key = cache_key(&source);
+        let cached = cache.zip(key.as_deref()).and_then(|(c, key)| c.load(key));
+        let snapshot_id = match &source {
+            SpotItem::Playlist(sp) => Some(sp.snapshot_id.clone()),
+            _ => None,
+        };
+        if let Some(cached) = &cached {
+            out.emit(CmdOut::ReplaceAll(cached.items.clone()));
+            // The playlist may come from a cached listing, older than the one cached here.
+            let live = match &source {
+                SpotItem::Playlist(sp) => live_snapshots()
+                    .lock()
+                    .unwrap()
+                    .get(&sp.id.uri())
+                    .is_some_and(|live| *live == sp.snapshot_id),
+                _ => false,
+            };
+            if live && cached.snapshot_id == snapshot_id {
+                debug!("{} is unchanged since it was cached", source.name());
+                return;
+            }
+        }
+        let offline = Offline::global();
+        if !offline.is_online() {
+            debug!("Offline, not fetching {}", source.name());
+            return;
+        }
+        let fetched = Mutex::new(Vec::new());
+        let add = |entry: ListEntry| {
+            // With nothing cached, show the items as they come.
+            if cached.is_none() {
+                out.emit(CmdOut::AddItem(entry.clone()));
+            }
+            fetched.lock().unwrap().push(entry);
+        };
+        let result = Model::fetch(source.clone(), add).await;
+        offline.observe(&result);
+        if let Err(e) = result {
+            error!("Failed to load {}: {:?}", source.name(), e);
+            return;
+        }
+        let fetched = fetched.into_inner().unwrap();
+        {
+            let mut live = live_snapshots().lock().unwrap();
+            for entry in &fetched {
+                if let SpotItem::Playlist(sp) = &entry.item {
+                    live.insert(sp.id.uri(), sp.snapshot_id.clone());
                 }
-            },
-            SpotItem::SearchResults { st, query } => sender.command(move |out, shutdown| {
-                SpotConn::global().search(st, query, move |item| out.emit(CmdOut::AddItem(item)))
-            }),
+            }
+        }
+        match cached {
+            Some(cached) => {
+                if serde_json::to_value(&cached.items).ok() != serde_json::to_value(&fetched).ok() {
+                    out.emit(CmdOut::ReplaceAll(fetched.clone()));
+                }
+            }
+            None => out.emit(CmdOut::ReplaceAll(fetched.clone())),
+        }
+        if let Some((cache, key)) = cache.zip(key) {
+            cache.store(&key, snapshot_id.as_deref(), &fetched);
+        }
+    }
+
+    /// Fetches the contents of the source from the Web API.
+    async fn fetch<F>(source: SpotItem, add_entry: F) -> ClientResult<()>
+    where
+        F: Fn(ListEntry),
+    {
+        let spot = SpotConn::global();
+        let add = |item: SpotItem| add_entry(item.into());
+        match source {
+            SpotItem::UserPlaylists => {
+                spot.current_user_playlists(|sp| add(SpotItem::Playlist(sp)))
+                    .await
+            }
+            SpotItem::Playlist(sp) => spot.playlist_items(sp.id.uri(), add_entry).await,
+            SpotItem::SavedShows => spot.saved_shows(|sh| add(SpotItem::Show(sh))).await,
+            SpotItem::Show(sh) => {
+                spot.show_episodes(sh.id.uri(), |ep| add(SpotItem::Episode(ep)))
+                    .await
+            }
+            SpotItem::RecentlyPlayed => spot.recently_played(|ft| add(SpotItem::Track(ft))).await,
+            SpotItem::TopTracks(range) => {
+                spot.top_tracks(range, |ft| add(SpotItem::Track(ft))).await
+            }
+            SpotItem::TopArtists(range) => {
+                spot.top_artists(range, |a| add(SpotItem::Artist(a))).await
+            }
+            SpotItem::Artist(a) => {
+                spot.artist_albums(a.id.uri(), |album| add(SpotItem::Album(album)))
+                    .await
+            }
+            SpotItem::Album(a) => {
+                let uri = a.id.map(|id| id.uri()).unwrap_or_default();
+                spot.album_tracks(uri, |ft| add(SpotItem::Track(ft))).await
+            }
+            SpotItem::Radio(seed) => {
+                spot.recommendations(seed, |ft| add(SpotItem::Track(ft)))
+                    .await
+            }
+            SpotItem::Devices => {
+                let devices = spot.devices().await?;
+                devices
+                    .into_iter()
+                    .for_each(|dev| add(SpotItem::Device(dev)));
+                Ok(())
+            }
+            SpotItem::SearchResults { st, query } => spot.search(st, query, add).await,
+            SpotItem::Track(_) | SpotItem::Episode(_) | SpotItem::Device(_) => {
+                unreachable!("checked in init_data_loading")
+            }
         }
     }
 
     fn move_cursor(&mut self, delta: i32, sender: &FactorySender<Self>) {
-        let next_id = match self.cursor.clone() {
+        let len = self.dense_items.len() as i32;
+        let mut next_id = match self.cursor.clone() {
             Some(cursor) => {
                 // TODO: remove the next line, has_cursor=false is handled in MoveCursorTo
                 self.dense_items
@@ -275,25 +959,33 @@ impl Model {
                     .unwrap()
                     .has_cursor = false;
                 self.cursor = None;
-                let idx = cursor.current_index() as i32;
-                if idx + delta < 0 {
-                    warn!("cursor up out of the list");
-                    sender.output_sender().emit(Out::Nav(NavOutput::EscapedUp));
-                    return;
-                }
-                if (idx + delta) as usize >= self.dense_items.len() {
-                    warn!("cursor down out of the list");
-                    sender
-                        .output_sender()
-                        .emit(Out::Nav(NavOutput::EscapedDown));
-                    return;
-                }
-                (idx + delta) as usize
+                cursor.current_index() as i32 + delta
             }
             None if delta > 0 => 0,
-            None => self.dense_items.len() - 1,
+            None => len - 1,
         };
-        match self.dense_items.get(next_id) {
+        if Settings::get().skip_unplayable {
+            while (0..len).contains(&next_id)
+                && !self.entries[self.shown[next_id as usize]]
+                    .item
+                    .is_playable()
+            {
+                next_id += delta.signum();
+            }
+        }
+        if next_id < 0 {
+            warn!("cursor up out of the list");
+            sender.output_sender().emit(Out::Nav(NavOutput::EscapedUp));
+            return;
+        }
+        if next_id >= len {
+            warn!("cursor down out of the list");
+            sender
+                .output_sender()
+                .emit(Out::Nav(NavOutput::EscapedDown));
+            return;
+        }
+        match self.dense_items.get(next_id as usize) {
             Some(next) => sender
                 .input_sender()
                 .emit(In::MoveCursorTo(next.self_idx.clone())),
@@ -309,6 +1001,9 @@ impl Model {
 struct ChildItem {
     sb: Controller<smallblock::Model>,
     has_cursor: bool,
+    selected: bool,
+    /// Like "Disc 2", above the first track of each disc.
+    heading: Option<String>,
     self_idx: DynamicIndex,
 }
 
@@ -322,7 +1017,7 @@ enum ChildOut {
 
 #[relm4::factory]
 impl FactoryComponent for ChildItem {
-    type Init = SpotItem;
+    type Init = smallblock::Init;
     type Input = ChildIn;
     type Output = ChildOut;
     type ParentWidget = gtk::Box;
@@ -338,7 +1033,17 @@ impl FactoryComponent for ChildItem {
             set_css_classes: &["dense-item"],
             #[watch]
             set_class_active: ("has-cursor", self.has_cursor),
+            #[watch]
+            set_class_active: ("selected", self.selected),
             set_orientation: gtk::Orientation::Vertical,
+            gtk::Label {
+                set_css_classes: &["disc"],
+                set_xalign: 0.0,
+                #[watch]
+                set_label: self.heading.as_deref().unwrap_or_default(),
+                #[watch]
+                set_visible: self.heading.is_some(),
+            },
             self.sb.widget() {}
         }
     }
@@ -353,7 +1058,15 @@ impl FactoryComponent for ChildItem {
         ChildItem {
             sb,
             has_cursor: false,
+            selected: false,
+            heading: None,
             self_idx: index.clone(),
         }
     }
 }
+
+/// Snapshot ids of playlists as last fetched from Spotify in this run, by uri.
+fn live_snapshots() -> &'static Mutex<HashMap<String, String>> {
+    static LIVE: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+    LIVE.get_or_init(Mutex::default)
+}
@@ -0,0 +1,225 @@
+//! Header of a playlist or album page: large cover art, owner or artists,
+//! description and a line of facts like track count and total duration.
+//! Hidden for any other kind of list.
+
+use gtk::{gdk_pixbuf::Pixbuf, glib, prelude::*};
+use log::{debug, error};
+use relm4::prelude::*;
+
+use crate::offline::Offline;
+use crate::spotconn::model::{format_duration, ListHeader, SpotItem};
+use crate::spotconn::SpotConn;
+
+const COVER_SIZE: i32 = 160;
+
+#[derive(Debug)]
+pub struct Model {
+    source: SpotItem,
+    pixbuf: Option<Pixbuf>,
+    /// Loaded asynchronously, the header shows without it meanwhile.
+    info: ListHeader,
+    /// Number of tracks and episodes loaded so far, and their total length.
+    count: usize,
+    duration: chrono::Duration,
+}
+
+#[derive(Debug)]
+pub enum In {
+    Reset(SpotItem),
+    /// The list below now has this many items, of this total length.
+    Items {
+        count: usize,
+        duration: chrono::Duration,
+    },
+}
+
+#[derive(Debug)]
+pub enum CmdOut {
+    ImageLoaded(Result<glib::Bytes, String>),
+    Loaded(ListHeader),
+}
+
+#[relm4::component(pub)]
+#[allow(deprecated)]
+impl Component for Model {
+    type Init = SpotItem;
+    type Input = In;
+    type Output = ();
+    type CommandOutput = CmdOut;
+
+    view! {
+        #[root]
+        gtk::Box::new(gtk::Orientation::Horizontal, 12) {
+            set_css_classes: &["list-header"],
+            #[watch]
+            set_visible: Model::has_header(&model.source),
+
+            gtk::Image {
+                set_pixel_size: COVER_SIZE,
+                #[watch]
+                set_from_pixbuf: model.pixbuf.as_ref(),
+            },
+            gtk::Box::new(gtk::Orientation::Vertical, 4) {
+                set_valign: gtk::Align::End,
+
+                gtk::Label {
+                    set_css_classes: &["title"],
+                    set_xalign: 0.0,
+                    set_wrap: true,
+                    #[watch]
+                    set_label: &model.source.name(),
+                },
+                gtk::Label {
+                    set_xalign: 0.0,
+                    #[watch]
+                    set_label: &model.byline(),
+                },
+                gtk::Label {
+                    set_xalign: 0.0,
+                    set_wrap: true,
+                    #[watch]
+                    set_label: model.info.description.as_deref().unwrap_or_default(),
+                    #[watch]
+                    set_visible: model.info.description.is_some(),
+                },
+                gtk::Label {
+                    set_css_classes: &["dim"],
+                    set_xalign: 0.0,
+                    #[watch]
+                    set_label: &model.facts(),
+                },
+            },
+        }
+    }
+
+    fn init(
+        source: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = Model {
+            source,
+            pixbuf: None,
+            info: ListHeader::default(),
+            count: 0,
+            duration: chrono::Duration::zero(),
+        };
+        let widgets = view_output!();
+        model.load(&sender);
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: In, sender: ComponentSender<Self>, _root: &Self::Root) {
+        match msg {
+            In::Reset(source) => {
+                self.source = source;
+                self.pixbuf = None;
+                self.info = ListHeader::default();
+                self.load(&sender);
+            }
+            In::Items { count, duration } => {
+                self.count = count;
+                self.duration = duration;
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        msg: Self::CommandOutput,
+        _sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match msg {
+            CmdOut::ImageLoaded(Err(e)) => {
+                debug!("No cover for {}: {}", self.source.name(), e);
+            }
+            CmdOut::ImageLoaded(Ok(bytes)) => {
+                let stream = gtk::gio::MemoryInputStream::from_bytes(&bytes);
+                match Pixbuf::from_stream_at_scale(
+                    &stream,
+                    COVER_SIZE,
+                    COVER_SIZE,
+                    true,
+                    gtk::gio::Cancellable::NONE,
+                ) {
+                    Ok(pixbuf) => self.pixbuf = Some(pixbuf),
+                    Err(e) => error!("Cannot decode the cover of {}: {}", self.source.name(), e),
+                }
+            }
+            CmdOut::Loaded(info) => self.info = info,
+        }
+    }
+}
+
+impl Model {
+    fn has_header(source: &SpotItem) -> bool {
+        matches!(source, SpotItem::Playlist(_) | SpotItem::Album(_))
+    }
+
+    /// Fetches the cover and the extra details of the source.
+    fn load(&self, sender: &ComponentSender<Self>) {
+        if !Model::has_header(&self.source) {
+            return;
+        }
+        if let Some(img_url) = self.source.img_url().map(str::to_string) {
+            sender.oneshot_command(async move {
+                let bytes = async { reqwest::get(img_url).await?.bytes().await }.await;
+                CmdOut::ImageLoaded(
+                    bytes
+                        .map(|b| glib::Bytes::from_owned(b.to_vec()))
+                        .map_err(|e| e.to_string()),
+                )
+            });
+        }
+        let source = self.source.clone();
+        sender.oneshot_command(async move {
+            let result = SpotConn::global().list_header(&source).await;
+            Offline::global().observe(&result);
+            match result {
+                Ok(info) => CmdOut::Loaded(info),
+                Err(e) => {
+                    error!("Failed to load the header of {}: {:?}", source.name(), e);
+                    CmdOut::Loaded(ListHeader::default())
+                }
+            }
+        });
+    }
+
+    fn byline(&self) -> String {
+        match &self.source {
+            SpotItem::Playlist(_) => format!("Playlist by {}", self.source.artist()),
+            SpotItem::Album(a) => {
+                let kind = match a.album_type.as_deref() {
+                    Some("single") => "Single",
+                    Some("compilation") => "Compilation",
+                    _ => "Album",
+                };
+                format!("{} by {}", kind, self.source.artist())
+            }
+            _ => "".to_string(),
+        }
+    }
+
+    /// E.g. "12 tracks · 48:10 · released 2021-03-05 · Warp Records".
+    fn facts(&self) -> String {
+        let mut facts = vec![];
+        if self.count > 0 {
+            let noun = if self.count == 1 { "track" } else { "tracks" };
+            facts.push(format!("{} {}", self.count, noun));
+            facts.push(format_duration(self.duration));
+        }
+        if let Some(followers) = self.info.followers {
+            facts.push(format!("{} followers", followers));
+        }
+        if let SpotItem::Album(a) = &self.source {
+            if let Some(date) = &a.release_date {
+                facts.push(format!("released {}", date));
+            }
+        }
+        if let Some(label) = &self.info.label {
+            facts.push(label.clone());
+        }
+        facts.join(" · ")
+    }
+}
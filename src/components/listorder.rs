@@ -0,0 +1,294 @@
+//! Client-side order of a denselist: sorted by a field of the items, optionally grouped
+//! by artist or album. The list keeps the original order too, to play and to go back to.
+
+use std::cmp::Ordering;
+
+use crate::spotconn::model::{ListEntry, SpotItem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    Name,
+    Artist,
+    Album,
+    ReleaseDate,
+    AddedAt,
+    Duration,
+}
+
+impl SortField {
+    /// In the order the sort key cycles through them.
+    const ALL: [SortField; 6] = [
+        SortField::Name,
+        SortField::Artist,
+        SortField::Album,
+        SortField::ReleaseDate,
+        SortField::AddedAt,
+        SortField::Duration,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            SortField::Name => "name",
+            SortField::Artist => "artist",
+            SortField::Album => "album",
+            SortField::ReleaseDate => "release date",
+            SortField::AddedAt => "date added",
+            SortField::Duration => "duration",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    Artist,
+    Album,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SortCommand {
+    /// Sort by the next field: name, artist, ..., duration, then the original order again.
+    NextField,
+    /// Flip between ascending and descending.
+    Reverse,
+    /// Group by artist, then by album, then not at all.
+    NextGrouping,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ListOrder {
+    /// None keeps the order the list came in.
+    field: Option<SortField>,
+    descending: bool,
+    grouping: Option<Grouping>,
+}
+
+impl ListOrder {
+    pub fn apply(&mut self, cmd: SortCommand) {
+        match cmd {
+            SortCommand::NextField => {
+                let next = match self.field {
+                    None => Some(0),
+                    Some(f) => SortField::ALL
+                        .iter()
+                        .position(|other| *other == f)
+                        .map(|idx| idx + 1)
+                        .filter(|idx| *idx < SortField::ALL.len()),
+                };
+                self.field = next.map(|idx| SortField::ALL[idx]);
+            }
+            SortCommand::Reverse => self.descending = !self.descending,
+            SortCommand::NextGrouping => {
+                self.grouping = match self.grouping {
+                    None => Some(Grouping::Artist),
+                    Some(Grouping::Artist) => Some(Grouping::Album),
+                    Some(Grouping::Album) => None,
+                }
+            }
+        }
+    }
+
+    /// Whether the list shows in the order it came in.
+    pub fn is_original(&self) -> bool {
+        self.field.is_none() && !self.descending && self.grouping.is_none()
+    }
+
+    pub fn is_grouped(&self) -> bool {
+        self.grouping.is_some()
+    }
+
+    /// E.g. "by artist, sorted by duration, descending". Empty for the original order.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![];
+        match self.grouping {
+            Some(Grouping::Artist) => parts.push("by artist".to_string()),
+            Some(Grouping::Album) => parts.push("by album".to_string()),
+            None => {}
+        }
+        if let Some(field) = self.field {
+            parts.push(format!("sorted by {}", field.title()));
+        }
+        match (self.field, self.descending) {
+            (Some(_), true) => parts.push("descending".to_string()),
+            (None, true) => parts.push("reversed".to_string()),
+            (_, false) => {}
+        }
+        parts.join(", ")
+    }
+
+    /// Indices of the entries, in display order.
+    pub fn sorted(&self, entries: &[ListEntry]) -> Vec<usize> {
+        let mut shown: Vec<usize> = (0..entries.len()).collect();
+        // Stable, so that equal entries keep their original order.
+        shown.sort_by(|a, b| self.compare(entries, *a, *b));
+        shown
+    }
+
+    /// Where the entry at idx, just added, goes among the shown ones.
+    pub fn insertion_point(&self, entries: &[ListEntry], shown: &[usize], idx: usize) -> usize {
+        shown.partition_point(|other| self.compare(entries, *other, idx) != Ordering::Greater)
+    }
+
+    /// Compares the entries at the two indices.
+    fn compare(&self, entries: &[ListEntry], a_idx: usize, b_idx: usize) -> Ordering {
+        let (a, b) = (&entries[a_idx], &entries[b_idx]);
+        let by_group = compare_present(self.group_of(a), self.group_of(b), false);
+        let by_field = match self.field {
+            Some(SortField::Name) => {
+                compare_present(text(a.item.name()), text(b.item.name()), self.descending)
+            }
+            Some(SortField::Artist) => compare_present(
+                text(a.item.artist()),
+                text(b.item.artist()),
+                self.descending,
+            ),
+            Some(SortField::Album) => compare_present(
+                album_of(&a.item).and_then(text),
+                album_of(&b.item).and_then(text),
+                self.descending,
+            ),
+            Some(SortField::ReleaseDate) => compare_present(
+                release_date(&a.item),
+                release_date(&b.item),
+                self.descending,
+            ),
+            Some(SortField::AddedAt) => compare_present(
+                a.added.as_ref().and_then(|added| added.at),
+                b.added.as_ref().and_then(|added| added.at),
+                self.descending,
+            ),
+            Some(SortField::Duration) => {
+                compare_present(a.item.duration(), b.item.duration(), self.descending)
+            }
+            None if self.descending => b_idx.cmp(&a_idx),
+            None => Ordering::Equal,
+        };
+        by_group.then(by_field)
+    }
+
+    /// Heading of the group the entry is in, like the artist name.
+    pub fn group_of(&self, entry: &ListEntry) -> Option<String> {
+        match self.grouping? {
+            Grouping::Artist => Some(entry.item.artist()).filter(|artist| !artist.is_empty()),
+            Grouping::Album => album_of(&entry.item),
+        }
+    }
+}
+
+/// Compares the values, with the missing ones last either way.
+fn compare_present<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if descending => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Case-insensitive key for sorting names.
+fn text(s: String) -> Option<String> {
+    Some(s.to_lowercase()).filter(|s| !s.is_empty())
+}
+
+fn album_of(item: &SpotItem) -> Option<String> {
+    match item {
+        SpotItem::Track(ft) => Some(ft.album.name.clone()),
+        SpotItem::Episode(ep) => Some(ep.show.name.clone()),
+        SpotItem::Album(a) => Some(a.name.clone()),
+        _ => None,
+    }
+}
+
+/// Like "2021-03-05", or just "2021": compares fine as a string either way.
+fn release_date(item: &SpotItem) -> Option<String> {
+    match item {
+        SpotItem::Track(ft) => ft.album.release_date.clone(),
+        SpotItem::Episode(ep) => Some(ep.release_date.clone()),
+        SpotItem::Album(a) => a.release_date.clone(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn album(name: &str, artist: &str, released: Option<&str>) -> ListEntry {
+        let album = serde_json::from_value(json!({
+            "artists": [{"external_urls": {}, "name": artist}],
+            "external_urls": {},
+            "images": [],
+            "name": name,
+            "release_date": released,
+        }))
+        .unwrap();
+        SpotItem::Album(album).into()
+    }
+
+    fn entries() -> Vec<ListEntry> {
+        vec![
+            album("Discovery", "Daft Punk", Some("2001-03-12")),
+            album("amnesiac", "Radiohead", None),
+            album("Homework", "Daft Punk", Some("1997")),
+            album("Kid A", "Radiohead", Some("2000-10-02")),
+        ]
+    }
+
+    fn order(cmds: &[SortCommand]) -> ListOrder {
+        let mut order = ListOrder::default();
+        for cmd in cmds {
+            order.apply(*cmd);
+        }
+        order
+    }
+
+    #[test]
+    fn keeps_the_original_order() {
+        let original = ListOrder::default();
+        assert!(original.is_original());
+        assert_eq!(original.sorted(&entries()), vec![0, 1, 2, 3]);
+        let reversed = order(&[SortCommand::Reverse]);
+        assert_eq!(reversed.sorted(&entries()), vec![3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn sorts_names_ignoring_case() {
+        let order = order(&[SortCommand::NextField]);
+        assert_eq!(order.describe(), "sorted by name");
+        assert_eq!(order.sorted(&entries()), vec![1, 0, 2, 3]);
+    }
+
+    #[test]
+    fn puts_missing_values_last_either_way() {
+        let cmds = [SortCommand::NextField; 4];
+        let ascending = order(&cmds);
+        assert_eq!(ascending.describe(), "sorted by release date");
+        assert_eq!(ascending.sorted(&entries()), vec![2, 3, 0, 1]);
+
+        let descending = order(&[&cmds[..], &[SortCommand::Reverse]].concat());
+        assert_eq!(descending.sorted(&entries()), vec![0, 3, 2, 1]);
+    }
+
+    #[test]
+    fn groups_then_sorts_within_groups() {
+        let order = order(&[SortCommand::NextGrouping, SortCommand::NextField]);
+        assert_eq!(order.describe(), "by artist, sorted by name");
+        assert_eq!(order.sorted(&entries()), vec![0, 2, 1, 3]);
+        assert_eq!(order.group_of(&entries()[3]).as_deref(), Some("Radiohead"));
+    }
+
+    #[test]
+    fn inserts_where_sorting_would_put_it() {
+        let mut entries = entries();
+        let order = order(&[SortCommand::NextField]);
+        let shown = order.sorted(&entries);
+        entries.push(album("Hail to the Thief", "Radiohead", None));
+        assert_eq!(order.insertion_point(&entries, &shown, 4), 2);
+
+        // Unsorted, additions go at the end.
+        let original = ListOrder::default();
+        assert_eq!(original.insertion_point(&entries, &[0, 1, 2, 3], 4), 4);
+    }
+}
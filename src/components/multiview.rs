@@ -2,12 +2,13 @@
 //! For example, an Artist page is a multiview of albums, songs, and related artists.
 
 use crate::navigation::{NavCommand, NavOutput};
+use crate::spotconn::model::{LibraryChange, PlayTarget, SpotItem};
 use gtk::prelude::*;
 use log::{debug, warn};
 use relm4::{factory::FactoryVecDeque, prelude::*};
-use rspotify::model::{Offset, PlayContextId};
 
-use super::denselist;
+use super::denselist::{self, SelectCommand};
+use super::listorder::SortCommand;
 
 #[derive(Debug)]
 pub struct Model {
@@ -25,6 +26,13 @@ pub enum In {
     ResetSections(Vec<denselist::Init>),
     NextSection,
     PrevSection,
+    /// Put the cursor on the item with this uri in the section, once it loads.
+    RestoreCursor(usize, String),
+    Library(LibraryChange),
+    RefreshDevices,
+    /// Change the order of the current section.
+    Sort(SortCommand),
+    Select(SelectCommand),
     #[doc(hidden)]
     ForwardNavOut(NavOutput),
 }
@@ -43,10 +51,6 @@ impl SimpleComponent for Model {
     view! {
         #[root]
         gtk::Box::new(gtk::Orientation::Vertical, 0) {
-            gtk::Label {
-                set_label: "multiview",
-            },
-
             #[local_ref]
             sections_widget -> gtk::Box {
                 set_orientation: gtk::Orientation::Vertical,
@@ -104,6 +108,35 @@ impl SimpleComponent for Model {
             }
             In::NextSection => self.change_section(1),
             In::PrevSection => self.change_section(-1),
+            In::RestoreCursor(section, uri) => {
+                if section < self.sections.len() {
+                    self.cur_section = section;
+                    self.sections
+                        .send(section, denselist::In::RestoreCursor(uri));
+                } else {
+                    warn!(
+                        "Cannot restore cursor in section {}, out of bounds",
+                        section
+                    );
+                }
+            }
+            In::Library(change) => {
+                for idx in 0..self.sections.len() {
+                    self.sections
+                        .send(idx, denselist::In::Library(change.clone()));
+                }
+            }
+            In::RefreshDevices => {
+                for idx in 0..self.sections.len() {
+                    self.sections.send(idx, denselist::In::RefreshDevices);
+                }
+            }
+            In::Sort(cmd) => self
+                .sections
+                .send(self.cur_section, denselist::In::Sort(cmd)),
+            In::Select(cmd) => self
+                .sections
+                .send(self.cur_section, denselist::In::Select(cmd)),
             In::ForwardNavOut(nav_out) => sender.output_sender().emit(Out::Nav(nav_out)),
         }
     }
@@ -138,9 +171,44 @@ impl Model {
             .and_then(|dl| dl.descend())
     }
 
-    pub fn play_context(&self) -> Option<(PlayContextId<'static>, Option<Offset>)> {
+    pub fn play_context(&self) -> Option<PlayTarget> {
         self.sections
             .get(self.cur_section)
             .and_then(|dl| dl.play_context())
     }
+
+    /// The item under cursor in the current section.
+    pub fn current_item(&self) -> Option<SpotItem> {
+        self.sections
+            .get(self.cur_section)
+            .and_then(|dl| dl.current_item())
+    }
+
+    /// The selected items in the current section, or the item under cursor.
+    pub fn selection(&self) -> Vec<SpotItem> {
+        self.sections
+            .get(self.cur_section)
+            .map(|dl| dl.selection())
+            .unwrap_or_default()
+    }
+
+    /// Section and uri of the item under cursor, to bring it back with RestoreCursor.
+    pub fn cursor(&self) -> Option<(usize, String)> {
+        Some((self.cur_section, self.current_item()?.uri()?))
+    }
+
+    /// The collection displayed in the current section.
+    pub fn current_source(&self) -> Option<SpotItem> {
+        self.sections
+            .get(self.cur_section)
+            .map(|dl| dl.source().clone())
+    }
+
+    /// All the items in the current section.
+    pub fn current_items(&self) -> Vec<SpotItem> {
+        self.sections
+            .get(self.cur_section)
+            .map(|dl| dl.items())
+            .unwrap_or_default()
+    }
 }
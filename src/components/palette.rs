@@ -0,0 +1,612 @@
+//! Palette is a modal window for running commands by name, opened with `:`.
+//! It fuzzy-matches every action registered through ActionBuilder and AccelManager,
+//! and parses ex-style commands with arguments, like `volume 40`.
+//! Tab completes commands, search types, device and playlist names.
+
+use std::sync::Mutex;
+
+use gtk::prelude::*;
+use log::error;
+use relm4::prelude::*;
+use rspotify::model::{Device, SearchType, SimplifiedPlaylist};
+
+use crate::actionbuilder::{registered_actions, RegisteredAction};
+use crate::spotconn::{model::parse_link, SpotConn};
+
+/// What the user asked for, for the app to carry out.
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// A registered action, by its full name like "global-navigation.down".
+    Action(String),
+    /// Search, for only one type of results if given.
+    Search(Option<SearchType>, String),
+    /// Open a Spotify URI or web link.
+    Open(String),
+    Volume(u8),
+    /// Transfer playback to the device.
+    Device(Device),
+    /// Open the playlist.
+    Playlist(SimplifiedPlaylist),
+}
+
+struct CommandSpec {
+    keyword: &'static str,
+    usage: &'static str,
+}
+
+const COMMANDS: [CommandSpec; 5] = [
+    CommandSpec {
+        keyword: "search",
+        usage: "[album|artist|track|playlist|show] <query>",
+    },
+    CommandSpec {
+        keyword: "open",
+        usage: "<spotify: URI or link>",
+    },
+    CommandSpec {
+        keyword: "volume",
+        usage: "<0 to 100>",
+    },
+    CommandSpec {
+        keyword: "device",
+        usage: "<name>",
+    },
+    CommandSpec {
+        keyword: "playlist",
+        usage: "<name>",
+    },
+];
+
+const SEARCH_TYPES: [(&str, SearchType); 5] = [
+    ("album", SearchType::Album),
+    ("artist", SearchType::Artist),
+    ("track", SearchType::Track),
+    ("playlist", SearchType::Playlist),
+    ("show", SearchType::Show),
+];
+
+/// What a row of the list stands for.
+#[derive(Debug, Clone)]
+enum Pick {
+    Action(String),
+    /// A command, still without its arguments.
+    Command(&'static str),
+    /// An argument for the command typed, like a device name.
+    Argument(String),
+}
+
+#[derive(Debug, Clone)]
+struct Row {
+    title: String,
+    /// Keyboard shortcuts of an action, usage of a command.
+    hint: String,
+    /// The text Tab puts in the entry.
+    completion: String,
+    pick: Pick,
+}
+
+#[derive(Debug)]
+pub struct Model {
+    actions: Vec<RegisteredAction>,
+    devices: Vec<Device>,
+    playlists: Vec<SimplifiedPlaylist>,
+    /// Matches for the text in the entry, best first.
+    rows: Vec<Row>,
+    /// Text put in the entry by Tab. While it is unchanged, the rows stay,
+    /// so that another Tab goes on to the next one.
+    completed: Option<String>,
+    error: Option<String>,
+
+    entry: gtk::Entry,
+    list: gtk::ListBox,
+    scroll: gtk::ScrolledWindow,
+}
+
+#[derive(Debug)]
+pub enum In {
+    Changed,
+    /// Move the selection in the list up or down.
+    Move(i32),
+    Complete,
+    Run,
+    RunRow(usize),
+    Cancel,
+}
+
+#[derive(Debug)]
+pub enum Out {
+    Run(Command),
+    Closed,
+}
+
+#[derive(Debug)]
+pub enum CmdOut {
+    DevicesLoaded(Vec<Device>),
+    PlaylistsLoaded(Vec<SimplifiedPlaylist>),
+}
+
+#[relm4::component(pub)]
+impl Component for Model {
+    type Init = ();
+    type Input = In;
+    type Output = Out;
+    type CommandOutput = CmdOut;
+
+    view! {
+        #[root]
+        gtk::Window {
+            set_modal: true,
+            set_title: Some("Commands"),
+            set_default_width: 480,
+            set_default_height: 360,
+            connect_close_request[sender] => move |_| {
+                sender.input(In::Cancel);
+                gtk::glib::Propagation::Stop
+            },
+            // In the capture phase, before the entry moves the focus on Tab.
+            add_controller = gtk::EventControllerKey {
+                set_propagation_phase: gtk::PropagationPhase::Capture,
+                connect_key_pressed[sender] => move |_, key, _, _| {
+                    let msg = match key {
+                        gtk::gdk::Key::Tab => In::Complete,
+                        gtk::gdk::Key::Escape => In::Cancel,
+                        gtk::gdk::Key::Down => In::Move(1),
+                        gtk::gdk::Key::Up => In::Move(-1),
+                        _ => return gtk::glib::Propagation::Proceed,
+                    };
+                    sender.input(msg);
+                    gtk::glib::Propagation::Stop
+                },
+            },
+
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_spacing: 8,
+                set_margin_all: 12,
+
+                #[name="entry"]
+                gtk::Entry {
+                    set_placeholder_text: Some("Command or action, Tab to complete"),
+                    connect_changed => In::Changed,
+                    connect_activate => In::Run,
+                },
+                gtk::Label {
+                    set_css_classes: &["error"],
+                    set_xalign: 0.0,
+                    set_wrap: true,
+                    #[watch]
+                    set_visible: model.error.is_some(),
+                    #[watch]
+                    set_label: model.error.as_deref().unwrap_or_default(),
+                },
+                #[name="scroll"]
+                gtk::ScrolledWindow {
+                    set_vexpand: true,
+                    #[name="list"]
+                    gtk::ListBox {
+                        set_css_classes: &["palette"],
+                        connect_row_activated[sender] => move |_, row| {
+                            sender.input(In::RunRow(row.index() as usize));
+                        },
+                    },
+                },
+            },
+        }
+    }
+
+    fn init(
+        _: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        sender.oneshot_command(async {
+            match SpotConn::global().devices().await {
+                Ok(devices) => CmdOut::DevicesLoaded(devices),
+                Err(e) => {
+                    error!("Could not list devices: {:?}", e);
+                    CmdOut::DevicesLoaded(vec![])
+                }
+            }
+        });
+        sender.oneshot_command(async {
+            let playlists = Mutex::new(Vec::new());
+            let result = SpotConn::global()
+                .current_user_playlists(|sp| playlists.lock().unwrap().push(sp))
+                .await;
+            if let Err(e) = result {
+                error!("Could not list playlists: {:?}", e);
+            }
+            CmdOut::PlaylistsLoaded(playlists.into_inner().unwrap())
+        });
+
+        let mut model = Model {
+            actions: registered_actions(),
+            devices: vec![],
+            playlists: vec![],
+            rows: vec![],
+            completed: None,
+            error: None,
+            entry: gtk::Entry::default(),
+            list: gtk::ListBox::default(),
+            scroll: gtk::ScrolledWindow::default(),
+        };
+        let widgets = view_output!();
+        model.entry = widgets.entry.clone();
+        model.list = widgets.list.clone();
+        model.scroll = widgets.scroll.clone();
+        model.refilter();
+        root.present();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
+        match message {
+            In::Changed => {
+                let text = self.entry.text();
+                if self.completed.as_deref() == Some(text.as_str()) {
+                    return;
+                }
+                self.completed = None;
+                self.error = None;
+                self.refilter();
+            }
+            In::Move(delta) => {
+                let idx = self.selected() as i32 + delta;
+                if (0..self.rows.len() as i32).contains(&idx) {
+                    self.select(idx as usize);
+                }
+            }
+            In::Complete => {
+                // Another Tab right after a completion goes on to the next row.
+                let mut idx = self.selected();
+                if self.completed.is_some() {
+                    idx = (idx + 1) % self.rows.len().max(1);
+                }
+                let Some(row) = self.rows.get(idx) else {
+                    return;
+                };
+                self.select(idx);
+                self.complete_to(row.completion.clone());
+            }
+            In::Run => {
+                let text = self.entry.text().to_string();
+                match split_command(&text) {
+                    Some((keyword, args)) if !args.is_empty() => {
+                        let picked = match self.rows.get(self.selected()) {
+                            Some(Row {
+                                pick: Pick::Argument(value),
+                                ..
+                            }) => Some(value.clone()),
+                            _ => None,
+                        };
+                        self.finish(self.command(keyword, args, picked), &sender, root);
+                    }
+                    _ => sender.input(In::RunRow(self.selected())),
+                }
+            }
+            In::RunRow(idx) => {
+                let Some(row) = self.rows.get(idx).cloned() else {
+                    self.error = Some("No such command or action.".to_string());
+                    return;
+                };
+                let text = self.entry.text().to_string();
+                match row.pick {
+                    Pick::Action(name) => self.finish(Ok(Command::Action(name)), &sender, root),
+                    Pick::Command(_) => self.complete_to(row.completion),
+                    Pick::Argument(value) => match split_command(&text) {
+                        Some(("search", _)) => self.complete_to(row.completion),
+                        Some((keyword, _)) => {
+                            self.finish(self.command(keyword, &value, None), &sender, root)
+                        }
+                        None => self.complete_to(row.completion),
+                    },
+                }
+            }
+            In::Cancel => {
+                root.destroy();
+                sender.output_sender().emit(Out::Closed);
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        _sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            CmdOut::DevicesLoaded(devices) => self.devices = devices,
+            CmdOut::PlaylistsLoaded(playlists) => self.playlists = playlists,
+        }
+        // The names may be what is being typed.
+        if self.completed.is_none() {
+            self.refilter();
+        }
+    }
+}
+
+impl Model {
+    /// Recomputes the rows for the text in the entry, and shows them.
+    fn refilter(&mut self) {
+        let text = self.entry.text().to_string();
+        self.rows = match split_command(&text) {
+            Some((keyword, args)) => ranked(args, self.arguments(keyword)),
+            None => ranked(text.trim(), self.commands_and_actions()),
+        };
+        self.list.remove_all();
+        for row in &self.rows {
+            let line = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+            let title = gtk::Label::new(Some(&row.title));
+            title.set_hexpand(true);
+            title.set_xalign(0.0);
+            line.append(&title);
+            let hint = gtk::Label::new(Some(&row.hint));
+            hint.set_css_classes(&["hint"]);
+            line.append(&hint);
+            self.list.append(&line);
+        }
+        self.select(0);
+    }
+
+    fn commands_and_actions(&self) -> Vec<Row> {
+        let commands = COMMANDS.iter().map(|spec| Row {
+            title: spec.keyword.to_string(),
+            hint: spec.usage.to_string(),
+            completion: format!("{} ", spec.keyword),
+            pick: Pick::Command(spec.keyword),
+        });
+        let actions = self.actions.iter().map(|action| {
+            let title = action_title(&action.name);
+            Row {
+                hint: accels_label(&action.accels),
+                completion: title.clone(),
+                title,
+                pick: Pick::Action(action.name.clone()),
+            }
+        });
+        commands.chain(actions).collect()
+    }
+
+    /// What can follow the command, as far as Tab can complete it.
+    fn arguments(&self, keyword: &'static str) -> Vec<Row> {
+        let argument = |value: &str, hint: String, completion: String| Row {
+            title: value.to_string(),
+            hint,
+            completion,
+            pick: Pick::Argument(value.to_string()),
+        };
+        match keyword {
+            "search" => SEARCH_TYPES
+                .iter()
+                .map(|(name, _)| argument(name, String::new(), format!("search {} ", name)))
+                .collect(),
+            "device" => self
+                .devices
+                .iter()
+                .map(|dev| {
+                    let hint = if dev.is_active { "playing" } else { "" };
+                    argument(&dev.name, hint.to_string(), format!("device {}", dev.name))
+                })
+                .collect(),
+            "playlist" => self
+                .playlists
+                .iter()
+                .map(|sp| {
+                    let hint = format!("{} tracks", sp.tracks.total);
+                    argument(&sp.name, hint, format!("playlist {}", sp.name))
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Turns the command with its arguments into what to run.
+    /// picked is the argument chosen in the list, if any, for names only matched partly.
+    fn command(
+        &self,
+        keyword: &str,
+        args: &str,
+        picked: Option<String>,
+    ) -> Result<Command, String> {
+        match keyword {
+            "search" => {
+                let typed = args
+                    .split_once(char::is_whitespace)
+                    .and_then(|(word, query)| Some((search_type(word)?, query.trim())));
+                let (st, query) = match typed {
+                    Some((st, query)) => (Some(st), query),
+                    None => (None, args),
+                };
+                if query.is_empty() {
+                    return Err("What to search for?".to_string());
+                }
+                Ok(Command::Search(st, query.to_string()))
+            }
+            "open" => match parse_link(args) {
+                Some(_) => Ok(Command::Open(args.to_string())),
+                None => Err(format!("{} is not a Spotify link.", args)),
+            },
+            "volume" => args
+                .trim_end_matches('%')
+                .parse::<u8>()
+                .ok()
+                .filter(|percent| *percent <= 100)
+                .map(Command::Volume)
+                .ok_or_else(|| format!("The volume goes from 0 to 100, not {}.", args)),
+            "device" => {
+                let name = picked.as_deref().unwrap_or(args);
+                self.devices
+                    .iter()
+                    .find(|dev| dev.name.eq_ignore_ascii_case(name))
+                    .map(|dev| Command::Device(dev.clone()))
+                    .ok_or_else(|| format!("No device called {}.", name))
+            }
+            "playlist" => {
+                let name = picked.as_deref().unwrap_or(args);
+                self.playlists
+                    .iter()
+                    .find(|sp| sp.name.eq_ignore_ascii_case(name))
+                    .map(|sp| Command::Playlist(sp.clone()))
+                    .ok_or_else(|| format!("No playlist called {}.", name))
+            }
+            other => Err(format!("Unknown command {}.", other)),
+        }
+    }
+
+    /// Closes the palette with the command run, or shows why it cannot run.
+    fn finish(
+        &mut self,
+        command: Result<Command, String>,
+        sender: &ComponentSender<Self>,
+        root: &gtk::Window,
+    ) {
+        match command {
+            Ok(command) => {
+                sender.output_sender().emit(Out::Run(command));
+                root.destroy();
+                sender.output_sender().emit(Out::Closed);
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    fn complete_to(&mut self, text: String) {
+        self.completed = Some(text.clone());
+        self.entry.set_text(&text);
+        self.entry.set_position(-1);
+    }
+
+    fn selected(&self) -> usize {
+        self.list
+            .selected_row()
+            .map(|row| row.index() as usize)
+            .unwrap_or(0)
+    }
+
+    fn select(&self, idx: usize) {
+        let row = self.list.row_at_index(idx as i32);
+        self.list.select_row(row.as_ref());
+        // Selecting does not scroll the list to the row, unlike moving the focus there.
+        if let Some(bounds) = row.and_then(|row| row.compute_bounds(&self.list)) {
+            let top = bounds.y() as f64;
+            self.scroll
+                .vadjustment()
+                .clamp_page(top, top + bounds.height() as f64);
+        }
+    }
+}
+
+/// Splits "volume 40" into the command and its arguments.
+/// None while the command itself is still being typed.
+fn split_command(text: &str) -> Option<(&'static str, &str)> {
+    let (word, args) = text.trim_start().split_once(char::is_whitespace)?;
+    COMMANDS
+        .iter()
+        .find(|spec| spec.keyword == word)
+        .map(|spec| (spec.keyword, args.trim()))
+}
+
+fn search_type(word: &str) -> Option<SearchType> {
+    SEARCH_TYPES
+        .iter()
+        .find(|(name, _)| *name == word)
+        .map(|(_, st)| *st)
+}
+
+/// The rows that match the pattern, best first. All of them, in order, for no pattern.
+fn ranked(pattern: &str, rows: Vec<Row>) -> Vec<Row> {
+    if pattern.is_empty() {
+        return rows;
+    }
+    let mut scored: Vec<(i32, Row)> = rows
+        .into_iter()
+        .filter_map(|row| Some((fuzzy_score(pattern, &row.title)?, row)))
+        .collect();
+    // Stable, so that equal matches keep their order.
+    scored.sort_by_key(|(score, _)| -score);
+    scored.into_iter().map(|(_, row)| row).collect()
+}
+
+/// How well the pattern matches the candidate, None if it does not.
+/// The letters of the pattern have to appear in order, though not next to each other.
+/// Runs of letters and starts of words score higher, and so do shorter candidates.
+fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut prev: Option<usize> = None;
+    let pattern = pattern.to_lowercase();
+    for pc in pattern.chars().filter(|c| !c.is_whitespace()) {
+        let found = next + candidate[next..].iter().position(|c| *c == pc)?;
+        score += 1;
+        if prev.is_some_and(|prev| prev + 1 == found) {
+            score += 4;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 2;
+        }
+        prev = Some(found);
+        next = found + 1;
+    }
+    Some(score * 100 - candidate.len() as i32)
+}
+
+/// "global-navigation.reset-top" becomes "reset top".
+fn action_title(name: &str) -> String {
+    let short = name.split_once('.').map_or(name, |(_, short)| short);
+    short.replace(['-', '_'], " ")
+}
+
+/// Like "Shift+T", as GTK shows the shortcuts.
+fn accels_label(accels: &[String]) -> String {
+    accels
+        .iter()
+        .map(|accel| match gtk::accelerator_parse(accel) {
+            Some((key, mods)) => gtk::accelerator_get_label(key, mods).to_string(),
+            None => accel.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fuzzy_score, split_command};
+
+    #[test]
+    fn splits_known_commands() {
+        assert_eq!(split_command("volume 40"), Some(("volume", "40")));
+        assert_eq!(
+            split_command("  search album kind of blue "),
+            Some(("search", "album kind of blue"))
+        );
+        assert_eq!(split_command("volume "), Some(("volume", "")));
+    }
+
+    #[test]
+    fn no_command_while_typing_or_unknown() {
+        assert_eq!(split_command("volume"), None);
+        assert_eq!(split_command("vol 40"), None);
+        assert_eq!(split_command("reset top"), None);
+    }
+
+    #[test]
+    fn matches_letters_in_order() {
+        assert!(fuzzy_score("rst", "reset top").is_some());
+        assert!(fuzzy_score("pot", "top").is_none());
+        assert!(fuzzy_score("xyz", "reset top").is_none());
+        // Case and spaces in the pattern do not matter.
+        assert_eq!(
+            fuzzy_score("Re T", "reset top"),
+            fuzzy_score("ret", "reset top")
+        );
+    }
+
+    #[test]
+    fn prefers_runs_and_shorter_candidates() {
+        assert!(fuzzy_score("new", "new playlist") > fuzzy_score("new", "next window"));
+        assert!(fuzzy_score("play", "play") > fuzzy_score("play", "play now"));
+    }
+}
@@ -0,0 +1,509 @@
+//! Playlistdialog is a small modal window for creating, editing and deleting playlists,
+//! and for adding and removing their items.
+//! The dialog performs the change itself, and reports the result as a LibraryChange.
+//! While offline, the change is queued instead, see offline.rs.
+
+use gtk::prelude::*;
+use log::{debug, error};
+use relm4::prelude::*;
+use rspotify::model::{PlayableId, SimplifiedPlaylist};
+use rspotify::prelude::*;
+
+use crate::offline::{is_offline_error, Offline, QueuedWrite};
+use crate::spotconn::{
+    model::{LibraryChange, PlaylistDetails, PlaylistEdit, SpotItem},
+    SpotConn,
+};
+
+/// Playlist operations, as requested by the user. Turned into Kind
+/// once we know which playlist (or list) the operation applies to.
+#[derive(Debug, Clone, Copy)]
+pub enum PlaylistOp {
+    Create,
+    CreateFromList,
+    Rename,
+    Describe,
+    Visibility,
+    Delete,
+    /// Add the selected items to a playlist picked in the dialog.
+    AddItems,
+    /// Remove the selected items from the playlist displayed.
+    RemoveItems,
+}
+
+#[derive(Debug, Clone)]
+pub enum Kind {
+    /// Create a new playlist, filled with the tracks from seed (possibly none).
+    Create {
+        name: String,
+        seed: Vec<SpotItem>,
+    },
+    Rename(SimplifiedPlaylist),
+    Describe(SimplifiedPlaylist),
+    Visibility(SimplifiedPlaylist),
+    Delete(SimplifiedPlaylist),
+    /// The playlist to add to is None until the user picks one.
+    AddItems {
+        items: Vec<SpotItem>,
+        to: Option<SimplifiedPlaylist>,
+    },
+    RemoveItems {
+        from: SimplifiedPlaylist,
+        items: Vec<SpotItem>,
+    },
+}
+
+impl Kind {
+    fn title(&self) -> &'static str {
+        match self {
+            Kind::Create { .. } => "New playlist",
+            Kind::Rename(_) => "Rename playlist",
+            Kind::Describe(_) => "Edit description",
+            Kind::Visibility(_) => "Playlist visibility",
+            Kind::Delete(_) => "Delete playlist",
+            Kind::AddItems { .. } => "Add to playlist",
+            Kind::RemoveItems { .. } => "Remove from playlist",
+        }
+    }
+
+    fn prompt(&self) -> String {
+        match self {
+            Kind::Create { seed, .. } if seed.is_empty() => "Name of the new playlist:".to_string(),
+            Kind::Create { seed, .. } => {
+                format!("Name of the new playlist with {} items:", seed.len())
+            }
+            Kind::Rename(sp) => format!("New name for {}:", sp.name),
+            Kind::Describe(sp) => format!("Description of {}:", sp.name),
+            Kind::Visibility(sp) => format!("Who can see and edit {}?", sp.name),
+            Kind::Delete(sp) => format!("Remove {} from your library?", sp.name),
+            Kind::AddItems { items, .. } => format!("Add {} to:", items_noun(items.len())),
+            Kind::RemoveItems { from, items } => {
+                format!("Remove {} from {}?", items_noun(items.len()), from.name)
+            }
+        }
+    }
+
+    fn confirm_label(&self) -> &'static str {
+        match self {
+            Kind::Create { .. } => "Create",
+            Kind::Delete(_) => "Delete",
+            Kind::AddItems { .. } => "Add",
+            Kind::RemoveItems { .. } => "Remove",
+            _ => "Save",
+        }
+    }
+
+    fn edits_name(&self) -> bool {
+        matches!(self, Kind::Create { .. } | Kind::Rename(_))
+    }
+
+    fn edits_description(&self) -> bool {
+        matches!(self, Kind::Create { .. } | Kind::Describe(_))
+    }
+
+    fn edits_visibility(&self) -> bool {
+        matches!(self, Kind::Create { .. } | Kind::Visibility(_))
+    }
+
+    fn picks_playlist(&self) -> bool {
+        matches!(self, Kind::AddItems { .. })
+    }
+
+    /// The change to an existing playlist. Empty for creation and deletion.
+    fn edit(&self, details: &PlaylistDetails) -> PlaylistEdit {
+        match self {
+            Kind::Rename(_) => PlaylistEdit {
+                name: Some(details.name.clone()),
+                ..Default::default()
+            },
+            Kind::Describe(_) => PlaylistEdit {
+                description: Some(details.description.clone()),
+                ..Default::default()
+            },
+            Kind::Visibility(_) => PlaylistEdit {
+                public: Some(details.public),
+                collaborative: Some(details.collaborative),
+                ..Default::default()
+            },
+            Kind::Create { .. }
+            | Kind::Delete(_)
+            | Kind::AddItems { .. }
+            | Kind::RemoveItems { .. } => PlaylistEdit::default(),
+        }
+    }
+}
+
+/// "1 item", "12 items".
+fn items_noun(count: usize) -> String {
+    if count == 1 {
+        "1 item".to_string()
+    } else {
+        format!("{} items", count)
+    }
+}
+
+#[derive(Debug)]
+pub struct Model {
+    kind: Kind,
+    /// Details as shown when the dialog opened. For existing playlists,
+    /// the description is loaded asynchronously, straight into the entry.
+    details: PlaylistDetails,
+    busy: bool,
+    error: Option<String>,
+    /// What the user can add items to, once loaded.
+    playlists: Vec<SimplifiedPlaylist>,
+
+    name_entry: gtk::Entry,
+    description_entry: gtk::Entry,
+    public_check: gtk::CheckButton,
+    collaborative_check: gtk::CheckButton,
+    playlist_choice: gtk::DropDown,
+}
+
+#[derive(Debug)]
+pub enum In {
+    Submit,
+    Cancel,
+}
+
+#[derive(Debug)]
+pub enum Out {
+    Changed(LibraryChange),
+    Closed,
+}
+
+#[derive(Debug)]
+pub enum CmdOut {
+    DescriptionLoaded(String),
+    PlaylistsLoaded(Vec<SimplifiedPlaylist>),
+    /// The change made, None if it was queued and is not visible yet.
+    Done(Result<Option<LibraryChange>, String>),
+}
+
+#[relm4::component(pub)]
+impl Component for Model {
+    type Init = Kind;
+    type Input = In;
+    type Output = Out;
+    type CommandOutput = CmdOut;
+
+    view! {
+        #[root]
+        gtk::Window {
+            set_modal: true,
+            set_title: Some(model.kind.title()),
+            set_default_width: 360,
+            connect_close_request[sender] => move |_| {
+                sender.input(In::Cancel);
+                gtk::glib::Propagation::Stop
+            },
+
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_spacing: 8,
+                set_margin_all: 12,
+
+                gtk::Label {
+                    set_label: &model.kind.prompt(),
+                    set_xalign: 0.0,
+                    set_wrap: true,
+                },
+                #[name="playlist_choice"]
+                gtk::DropDown::from_strings(&["Loading playlists…"]) {
+                    set_visible: model.kind.picks_playlist(),
+                },
+                #[name="name_entry"]
+                gtk::Entry {
+                    set_visible: model.kind.edits_name(),
+                    set_text: &model.details.name,
+                    set_placeholder_text: Some("Name"),
+                    connect_activate => In::Submit,
+                },
+                #[name="description_entry"]
+                gtk::Entry {
+                    set_visible: model.kind.edits_description(),
+                    set_placeholder_text: Some("Description"),
+                    connect_activate => In::Submit,
+                },
+                #[name="public_check"]
+                gtk::CheckButton {
+                    set_visible: model.kind.edits_visibility(),
+                    set_label: Some("Public"),
+                    set_active: model.details.public,
+                },
+                #[name="collaborative_check"]
+                gtk::CheckButton {
+                    set_visible: model.kind.edits_visibility(),
+                    set_label: Some("Collaborative"),
+                    set_active: model.details.collaborative,
+                },
+                gtk::Label {
+                    set_css_classes: &["error"],
+                    set_xalign: 0.0,
+                    set_wrap: true,
+                    #[watch]
+                    set_visible: model.error.is_some(),
+                    #[watch]
+                    set_label: model.error.as_deref().unwrap_or_default(),
+                },
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Horizontal,
+                    set_halign: gtk::Align::End,
+                    set_spacing: 8,
+                    gtk::Button {
+                        set_label: "Cancel",
+                        connect_clicked => In::Cancel,
+                    },
+                    gtk::Button {
+                        set_label: model.kind.confirm_label(),
+                        #[watch]
+                        set_sensitive: !model.busy,
+                        connect_clicked => In::Submit,
+                    },
+                },
+            },
+        }
+    }
+
+    fn init(
+        kind: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let details = match &kind {
+            Kind::Create { name, .. } => PlaylistDetails {
+                name: name.clone(),
+                ..Default::default()
+            },
+            Kind::Rename(sp) | Kind::Describe(sp) | Kind::Visibility(sp) | Kind::Delete(sp) => {
+                PlaylistDetails {
+                    name: sp.name.clone(),
+                    description: String::new(),
+                    public: sp.public.unwrap_or(false),
+                    collaborative: sp.collaborative,
+                }
+            }
+            Kind::AddItems { .. } | Kind::RemoveItems { .. } => PlaylistDetails::default(),
+        };
+        if let Kind::Describe(sp) = &kind {
+            let id = sp.id.clone();
+            sender.oneshot_command(async move {
+                match SpotConn::global().playlist_description(id).await {
+                    Ok(description) => CmdOut::DescriptionLoaded(description),
+                    Err(e) => CmdOut::Done(Err(format!("Could not load the description: {}", e))),
+                }
+            });
+        }
+        if kind.picks_playlist() {
+            sender.oneshot_command(async move {
+                match SpotConn::global().editable_playlists().await {
+                    Ok(playlists) => CmdOut::PlaylistsLoaded(playlists),
+                    Err(e) => CmdOut::Done(Err(format!("Could not load your playlists: {}", e))),
+                }
+            });
+        }
+
+        let mut model = Model {
+            kind,
+            details,
+            busy: false,
+            error: None,
+            playlists: vec![],
+            name_entry: gtk::Entry::default(),
+            description_entry: gtk::Entry::default(),
+            public_check: gtk::CheckButton::default(),
+            collaborative_check: gtk::CheckButton::default(),
+            playlist_choice: gtk::DropDown::default(),
+        };
+        let widgets = view_output!();
+        model.name_entry = widgets.name_entry.clone();
+        model.description_entry = widgets.description_entry.clone();
+        model.public_check = widgets.public_check.clone();
+        model.collaborative_check = widgets.collaborative_check.clone();
+        model.playlist_choice = widgets.playlist_choice.clone();
+        root.present();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
+        match message {
+            In::Submit => {
+                if self.busy {
+                    return;
+                }
+                let details = PlaylistDetails {
+                    name: self.name_entry.text().to_string(),
+                    description: self.description_entry.text().to_string(),
+                    public: self.public_check.is_active(),
+                    collaborative: self.collaborative_check.is_active(),
+                };
+                if self.kind.edits_name() && details.name.trim().is_empty() {
+                    self.error = Some("The name cannot be empty.".to_string());
+                    return;
+                }
+                let mut kind = self.kind.clone();
+                if let Kind::AddItems { to, .. } = &mut kind {
+                    let chosen = self.playlists.get(self.playlist_choice.selected() as usize);
+                    let Some(chosen) = chosen else {
+                        self.error = Some("Pick a playlist first.".to_string());
+                        return;
+                    };
+                    *to = Some(chosen.clone());
+                }
+                self.busy = true;
+                self.error = None;
+                sender.oneshot_command(async move {
+                    CmdOut::Done(
+                        Model::perform(kind, details)
+                            .await
+                            .map_err(|e| e.to_string()),
+                    )
+                });
+            }
+            In::Cancel => {
+                root.destroy();
+                sender.output_sender().emit(Out::Closed);
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            CmdOut::DescriptionLoaded(description) => self.description_entry.set_text(&description),
+            CmdOut::PlaylistsLoaded(playlists) => {
+                let names: Vec<&str> = playlists.iter().map(|sp| sp.name.as_str()).collect();
+                self.playlist_choice
+                    .set_model(Some(&gtk::StringList::new(&names)));
+                self.playlists = playlists;
+            }
+            CmdOut::Done(Ok(change)) => {
+                debug!("Playlist dialog finished with {:?}", change);
+                if let Some(change) = change {
+                    sender.output_sender().emit(Out::Changed(change));
+                }
+                sender.input(In::Cancel);
+            }
+            CmdOut::Done(Err(e)) => {
+                error!("Playlist operation failed: {}", e);
+                self.busy = false;
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+impl Model {
+    /// Performs the change, or queues it while offline. Returns how the library changes,
+    /// or None if it only will once the queued change goes through.
+    async fn perform(
+        kind: Kind,
+        details: PlaylistDetails,
+    ) -> rspotify::ClientResult<Option<LibraryChange>> {
+        let offline = Offline::global();
+        if offline.is_online() {
+            let result = Model::send(kind.clone(), details.clone()).await;
+            offline.observe(&result);
+            match result {
+                Err(e) if is_offline_error(&e) => {}
+                result => return result.map(Some),
+            }
+        }
+        Ok(Model::queue(kind, details))
+    }
+
+    async fn send(kind: Kind, details: PlaylistDetails) -> rspotify::ClientResult<LibraryChange> {
+        let spot = SpotConn::global();
+        let edit = kind.edit(&details);
+        match kind {
+            Kind::Create { seed, .. } => {
+                let seed: Vec<PlayableId<'static>> =
+                    seed.iter().filter_map(SpotItem::playable_id).collect();
+                let created = spot.create_playlist(&details, seed).await?;
+                Ok(LibraryChange::PlaylistCreated(created))
+            }
+            Kind::Rename(mut sp) | Kind::Describe(mut sp) | Kind::Visibility(mut sp) => {
+                spot.change_playlist_details(sp.id.clone(), &edit).await?;
+                edit.apply(&mut sp);
+                Ok(LibraryChange::PlaylistUpdated(sp))
+            }
+            Kind::Delete(sp) => {
+                spot.delete_playlist(sp.id.clone()).await?;
+                Ok(LibraryChange::PlaylistRemoved(sp.id.uri()))
+            }
+            Kind::AddItems {
+                items,
+                to: Some(mut sp),
+            } => {
+                let ids: Vec<PlayableId<'static>> =
+                    items.iter().filter_map(SpotItem::playable_id).collect();
+                let added = ids.len() as u32;
+                sp.snapshot_id = spot.add_to_playlist(sp.id.clone(), ids).await?;
+                sp.tracks.total += added;
+                Ok(LibraryChange::PlaylistUpdated(sp))
+            }
+            Kind::AddItems { to: None, .. } => unreachable!("the playlist is picked on submit"),
+            Kind::RemoveItems { mut from, items } => {
+                // Relinked tracks are in the playlist under their original id.
+                let ids: Vec<PlayableId<'static>> = items
+                    .iter()
+                    .filter_map(SpotItem::original_playable_id)
+                    .collect();
+                let removed = ids.len() as u32;
+                from.snapshot_id = spot.remove_from_playlist(from.id.clone(), ids).await?;
+                from.tracks.total = from.tracks.total.saturating_sub(removed);
+                Ok(LibraryChange::PlaylistUpdated(from))
+            }
+        }
+    }
+
+    /// Edits and deletions show up right away, as if they went through.
+    /// A playlist created offline has no id yet, so it only appears once really created,
+    /// and added or removed items once the playlist gets refetched.
+    fn queue(kind: Kind, details: PlaylistDetails) -> Option<LibraryChange> {
+        let offline = Offline::global();
+        let edit = kind.edit(&details);
+        match kind {
+            Kind::Create { seed, .. } => {
+                offline.enqueue(QueuedWrite::CreatePlaylist { details, seed });
+                None
+            }
+            Kind::Rename(mut sp) | Kind::Describe(mut sp) | Kind::Visibility(mut sp) => {
+                offline.enqueue(QueuedWrite::EditPlaylist {
+                    uri: sp.id.uri(),
+                    edit: edit.clone(),
+                });
+                edit.apply(&mut sp);
+                Some(LibraryChange::PlaylistUpdated(sp))
+            }
+            Kind::Delete(sp) => {
+                offline.enqueue(QueuedWrite::DeletePlaylist { uri: sp.id.uri() });
+                Some(LibraryChange::PlaylistRemoved(sp.id.uri()))
+            }
+            // The new snapshot is only known once the change goes through.
+            Kind::AddItems {
+                items,
+                to: Some(sp),
+            } => {
+                offline.enqueue(QueuedWrite::AddToPlaylist {
+                    uri: sp.id.uri(),
+                    items,
+                });
+                None
+            }
+            Kind::AddItems { to: None, .. } => unreachable!("the playlist is picked on submit"),
+            Kind::RemoveItems { from, items } => {
+                offline.enqueue(QueuedWrite::RemoveFromPlaylist {
+                    uri: from.id.uri(),
+                    items,
+                });
+                None
+            }
+        }
+    }
+}
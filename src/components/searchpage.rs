@@ -1,18 +1,26 @@
 use gtk::prelude::*;
 
 use relm4::prelude::*;
-use rspotify::model::{Offset, PlayContextId, SearchType};
+use rspotify::model::SearchType;
 
 use crate::{
-    components::{denselist, multiview},
+    components::{
+        denselist::{self, SelectCommand},
+        listorder::SortCommand,
+        multiview,
+    },
     navigation::{NavCommand, NavOutput},
-    spotconn::model::SpotItem,
+    spotconn::model::{LibraryChange, PlayTarget, SpotItem},
 };
 
 #[derive(Debug)]
 pub struct Model {
     searchbox: gtk::Entry,
     btn_go: gtk::Button,
+    /// The last executed query.
+    query: Option<String>,
+    /// The only type of results it was run for, if just one.
+    search_type: Option<SearchType>,
 
     multiview: Controller<multiview::Model>,
 }
@@ -22,7 +30,13 @@ pub enum In {
     FocusSearchbox,
     #[doc(hidden)]
     ExecuteSearch, // run the search for current query
+    /// Put the query in the searchbox and run it, for only one type of results if given.
+    Search(Option<SearchType>, String),
+    RestoreCursor(usize, String),
     Nav(NavCommand),
+    Library(LibraryChange),
+    Sort(SortCommand),
+    Select(SelectCommand),
 }
 
 #[derive(Debug)]
@@ -76,6 +90,8 @@ impl Component for Model {
         let model = Model {
             searchbox: widgets.searchbox.clone(),
             btn_go: widgets.btn_go.clone(),
+            query: None,
+            search_type: None,
             multiview,
         };
         sender.input_sender().emit(In::FocusSearchbox);
@@ -88,38 +104,80 @@ impl Component for Model {
             In::FocusSearchbox => {
                 self.searchbox.grab_focus();
             }
-            In::ExecuteSearch => {
-                let query = self.searchbox.text().to_string();
-
-                let sections = vec![
-                    denselist::Init {
-                        source: SpotItem::SearchResults {
-                            st: SearchType::Album,
-                            query: query.clone(),
-                        },
-                    },
-                    denselist::Init {
-                        source: SpotItem::SearchResults {
-                            st: SearchType::Track,
-                            query: query.clone(),
-                        },
-                    },
-                ];
-                self.multiview.emit(multiview::In::ResetSections(sections));
-                self.btn_go.grab_focus();
+            In::ExecuteSearch => self.search(None),
+            In::Search(st, query) => {
+                self.searchbox.set_text(&query);
+                self.search(st);
             }
+            In::RestoreCursor(section, uri) => self
+                .multiview
+                .emit(multiview::In::RestoreCursor(section, uri)),
             // TODO: moves across multiple lists
             In::Nav(nav_cmd) => self.multiview.emit(multiview::In::Nav(nav_cmd)),
+            In::Library(change) => self.multiview.emit(multiview::In::Library(change)),
+            In::Sort(cmd) => self.multiview.emit(multiview::In::Sort(cmd)),
+            In::Select(cmd) => self.multiview.emit(multiview::In::Select(cmd)),
         }
     }
 }
 
 impl Model {
+    /// Runs the query in the searchbox, with a section for albums and one for tracks,
+    /// or only one for the given type of results.
+    fn search(&mut self, st: Option<SearchType>) {
+        let types = match st {
+            Some(st) => vec![st],
+            None => vec![SearchType::Album, SearchType::Track],
+        };
+        let query = self.searchbox.text().to_string();
+        let sections = types
+            .iter()
+            .map(|st| denselist::Init {
+                source: SpotItem::SearchResults {
+                    st: *st,
+                    query: query.clone(),
+                },
+            })
+            .collect();
+        self.multiview.emit(multiview::In::ResetSections(sections));
+        self.btn_go.grab_focus();
+        self.query = Some(query);
+        self.search_type = st;
+    }
+
     pub fn descend(&self) -> Option<denselist::Init> {
         self.multiview.model().descend()
     }
 
-    pub fn play_context(&self) -> Option<(PlayContextId<'static>, Option<Offset>)> {
+    pub fn play_context(&self) -> Option<PlayTarget> {
         self.multiview.model().play_context()
     }
+
+    pub fn current_item(&self) -> Option<SpotItem> {
+        self.multiview.model().current_item()
+    }
+
+    pub fn current_source(&self) -> Option<SpotItem> {
+        self.multiview.model().current_source()
+    }
+
+    pub fn current_items(&self) -> Vec<SpotItem> {
+        self.multiview.model().current_items()
+    }
+
+    pub fn selection(&self) -> Vec<SpotItem> {
+        self.multiview.model().selection()
+    }
+
+    pub fn cursor(&self) -> Option<(usize, String)> {
+        self.multiview.model().cursor()
+    }
+
+    pub fn query(&self) -> Option<String> {
+        self.query.clone()
+    }
+
+    pub fn search_type(&self) -> Option<SearchType> {
+        self.search_type
+    }
 }
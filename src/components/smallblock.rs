@@ -2,18 +2,39 @@
 //! This has an image on the left, and some text on the right.
 
 use gtk::{gdk_pixbuf::Pixbuf, glib, prelude::*};
+use log::{debug, error};
 use relm4::{prelude::*, Component, ComponentParts};
 use std::fmt::Debug;
 
-use crate::spotconn::model::SpotItem;
+use crate::settings::{Column, Settings};
+use crate::spotconn::model::{format_duration, Added, ListEntry, SpotItem};
 
 /// Model holds the state for the displayed component.
 pub struct Model {
     /// The underlying data. Only set at initialization.
     init: SpotItem,
+    /// When the item was added to the playlist it is displayed in, if it is.
+    added: Option<Added>,
     /// The image associated with the entry (usually album art).
     /// Loaded asynchronously after initialization.
     pixbuf: Option<Pixbuf>,
+    columns: Vec<Column>,
+    columns_box: gtk::Box,
+}
+
+pub struct Init {
+    pub entry: ListEntry,
+    /// Details shown on the right, like the duration of a track.
+    pub columns: Vec<Column>,
+}
+
+impl From<SpotItem> for Init {
+    fn from(item: SpotItem) -> Self {
+        Init {
+            entry: item.into(),
+            columns: vec![],
+        }
+    }
 }
 
 impl Debug for Model {
@@ -32,6 +53,7 @@ impl Model {
 #[derive(Debug)]
 pub enum In {
     Clicked,
+    SetColumns(Vec<Column>),
 }
 
 #[derive(Debug)]
@@ -41,13 +63,13 @@ pub enum Out {
 
 #[derive(Debug)]
 pub enum CmdOut {
-    ImageLoaded(glib::Bytes),
+    ImageLoaded(Result<glib::Bytes, String>),
 }
 
 #[relm4::component(pub)]
 #[allow(deprecated)]
 impl Component for Model {
-    type Init = SpotItem;
+    type Init = Init;
     type Input = In;
     type Output = Out;
     type CommandOutput = CmdOut;
@@ -56,6 +78,9 @@ impl Component for Model {
         #[root]
         gtk::Button {
             set_css_classes: &["smallblock"],
+            // Greyed out, but still there to look at and open.
+            set_class_active: ("unplayable", !model.init.is_playable()),
+            set_tooltip_text: (!model.init.is_playable()).then_some("Not available in your country"),
             connect_clicked => In::Clicked,
             gtk::Box{
                 set_orientation: gtk::Orientation::Horizontal,
@@ -69,6 +94,7 @@ impl Component for Model {
                 gtk::Box {
                     set_css_classes: &["textpart"],
                     set_orientation: gtk::Orientation::Vertical,
+                    set_hexpand: true,
 
                     gtk::Label {
                         set_css_classes: &["name"],
@@ -81,6 +107,10 @@ impl Component for Model {
                         set_xalign: 0.0,
                     },
                 },
+                #[name="columns_box"]
+                gtk::Box::new(gtk::Orientation::Horizontal, 8) {
+                    set_valign: gtk::Align::Center,
+                },
             },
         }
     }
@@ -90,15 +120,38 @@ impl Component for Model {
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
-        let model = Model { init, pixbuf: None };
+        let mut model = Model {
+            init: init.entry.item,
+            added: init.entry.added,
+            pixbuf: None,
+            columns: init.columns,
+            columns_box: gtk::Box::default(),
+        };
         let widgets = view_output!();
+        model.columns_box = widgets.columns_box.clone();
+        model.show_columns();
+
+        // Dropped into a browser or a chat, the item becomes its open.spotify.com link.
+        if let Some(url) = model.init.web_url() {
+            let uri_list = glib::Bytes::from_owned(format!("{}\r\n", url));
+            let content = gtk::gdk::ContentProvider::new_union(&[
+                gtk::gdk::ContentProvider::for_bytes("text/uri-list", &uri_list),
+                gtk::gdk::ContentProvider::for_value(&url.to_value()),
+            ]);
+            let drag = gtk::DragSource::new();
+            drag.set_actions(gtk::gdk::DragAction::COPY);
+            drag.set_content(Some(&content));
+            root.add_controller(drag);
+        }
 
         if let Some(img_url) = model.init.img_url().map(str::to_string) {
             sender.oneshot_command(async move {
-                let result = reqwest::get(img_url).await.unwrap();
-                let bytes = result.bytes().await.unwrap().to_vec();
-                let bytes = glib::Bytes::from(&bytes.to_vec());
-                CmdOut::ImageLoaded(bytes)
+                let bytes = async { reqwest::get(img_url).await?.bytes().await }.await;
+                CmdOut::ImageLoaded(
+                    bytes
+                        .map(|b| glib::Bytes::from_owned(b.to_vec()))
+                        .map_err(|e| e.to_string()),
+                )
             });
         }
         ComponentParts { model, widgets }
@@ -107,6 +160,10 @@ impl Component for Model {
     fn update(&mut self, msg: In, sender: ComponentSender<Self>, _root: &Self::Root) {
         match msg {
             In::Clicked => sender.output_sender().emit(Out::Clicked),
+            In::SetColumns(columns) => {
+                self.columns = columns;
+                self.show_columns();
+            }
         }
     }
 
@@ -117,20 +174,96 @@ impl Component for Model {
         _root: &Self::Root,
     ) {
         match msg {
-            CmdOut::ImageLoaded(bytes) => {
+            // Without a cover, the placeholder stays.
+            CmdOut::ImageLoaded(Err(e)) => {
+                debug!("No cover for {}: {}", self.init.name(), e);
+            }
+            CmdOut::ImageLoaded(Ok(bytes)) => {
                 let stream = gtk::gio::MemoryInputStream::from_bytes(&bytes);
-                let pixbuf = Pixbuf::from_stream(&stream, gtk::gio::Cancellable::NONE).unwrap();
-                self.pixbuf = Some(pixbuf);
+                match Pixbuf::from_stream(&stream, gtk::gio::Cancellable::NONE) {
+                    Ok(pixbuf) => self.pixbuf = Some(pixbuf),
+                    Err(e) => error!("Cannot decode the cover of {}: {}", self.init.name(), e),
+                }
             }
         }
     }
 }
 
 impl Model {
+    fn show_columns(&self) {
+        while let Some(child) = self.columns_box.first_child() {
+            self.columns_box.remove(&child);
+        }
+        for column in &self.columns {
+            let label = gtk::Label::new(Some(&self.column_value(*column)));
+            label.set_css_classes(&["column"]);
+            label.set_xalign(1.0);
+            label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+            // Fixed widths, so that the columns line up across the list.
+            label.set_width_chars(match column {
+                Column::Duration => 7,
+                Column::TrackNumber | Column::Popularity => 3,
+                Column::Album => 20,
+                Column::AddedAt => 10,
+                Column::AddedBy => 12,
+                Column::Explicit => 2,
+            });
+            label.set_max_width_chars(label.width_chars());
+            if *column == Column::Explicit && !label.label().is_empty() {
+                label.add_css_class("explicit");
+            }
+            self.columns_box.append(&label);
+        }
+    }
+
+    fn column_value(&self, column: Column) -> String {
+        let added = self.added.as_ref();
+        match (column, &self.init) {
+            (Column::Duration, item) => item.duration().map(format_duration).unwrap_or_default(),
+            (Column::TrackNumber, SpotItem::Track(ft)) => ft.track_number.to_string(),
+            (Column::Album, SpotItem::Track(ft)) => ft.album.name.clone(),
+            (Column::Album, SpotItem::Episode(ep)) => ep.show.name.clone(),
+            (Column::AddedAt, _) => added
+                .and_then(|added| added.at)
+                .map(|at| at.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            (Column::AddedBy, _) => added.and_then(|added| added.by.clone()).unwrap_or_default(),
+            (Column::Explicit, SpotItem::Track(ft)) if ft.explicit => "E".to_string(),
+            (Column::Explicit, SpotItem::Episode(ep)) if ep.explicit => "E".to_string(),
+            (Column::Popularity, SpotItem::Track(ft)) => ft.popularity.to_string(),
+            _ => "".to_string(),
+        }
+    }
+
     fn second_line(&self) -> String {
         match &self.init {
             SpotItem::Album(a) => format!("Album by {}", self.init.artist()),
             SpotItem::Playlist(p) => format!("Playlist by {}", self.init.artist()),
+            SpotItem::Show(sh) => format!("Podcast by {}", self.init.artist()),
+            SpotItem::Artist(a) if a.genres.is_empty() => "Artist".to_string(),
+            SpotItem::Artist(a) => format!("Artist · {}", a.genres.join(", ")),
+            SpotItem::Episode(ep) => {
+                let progress = match (&ep.resume_point, self.init.resume_position()) {
+                    (Some(rp), _) if rp.fully_played => "played".to_string(),
+                    (_, Some(pos)) => format!("{} left", format_duration(ep.duration - pos)),
+                    _ => format_duration(ep.duration),
+                };
+                format!("{} · {}", self.init.artist(), progress)
+            }
+            SpotItem::Device(dev) => {
+                let mut parts = vec![format!("{:?}", dev._type)];
+                if let Some(volume) = dev.volume_percent {
+                    parts.push(format!("volume {}%", volume));
+                }
+                if dev.is_active {
+                    parts.push("active".to_string());
+                }
+                let preferred = Settings::get().preferred_device;
+                if preferred.is_some_and(|p| dev.id.as_deref() == Some(p.id.as_str())) {
+                    parts.push("preferred".to_string());
+                }
+                parts.join(" · ")
+            }
             other => other.artist(),
         }
     }
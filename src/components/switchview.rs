@@ -1,45 +1,157 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::rc::Rc;
+
 use gtk::prelude::*;
-use log::debug;
+use log::{debug, error};
 use relm4::{factory::FactoryVecDeque, prelude::*};
-use rspotify::model::Offset;
-use rspotify::model::PlayContextId;
+use rspotify::model::{FullTrack, SearchType, TimeRange};
+use serde::{Deserialize, Serialize};
 
-use super::denselist;
+use super::denselist::{self, SelectCommand};
+use super::listorder::SortCommand;
 use super::multiview;
 use super::searchpage;
+use super::toppage;
+use super::trackdetails;
 use crate::navigation::NavCommand;
 use crate::navigation::NavOutput;
-use crate::spotconn::model::SpotItem;
+use crate::spotconn::model::{LibraryChange, PlayTarget, SpotItem};
+
+/// How many recently visited pages the history popover lists.
+const HISTORY_LEN: usize = 20;
 
 pub struct Model {
     views: FactoryVecDeque<Child>,
+    /// Index of the page on screen. Pages after it were left with NavBack,
+    /// and stay alive so that NavForward brings them back as they were.
+    top: usize,
+    /// Recently visited pages, most recent first.
+    history: VecDeque<ChildLayout>,
+    history_button: gtk::MenuButton,
+    history_list: gtk::ListBox,
+    /// Titles of the pages from the root up to the one on screen.
+    breadcrumbs: gtk::Box,
+    input: relm4::Sender<In>,
     gtk_stack: gtk::Stack,
     scrollwin: gtk::ScrolledWindow,
+    /// The stack as of the last update, written to disk when the app shuts down.
+    session: Rc<RefCell<Session>>,
 }
 
 #[derive(Debug)]
 pub struct Init {}
 
-#[derive(Debug, Clone, Copy)]
+/// The page stack with the cursor of each page, restored at startup.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Session {
+    pages: Vec<ChildInit>,
+    top: usize,
+}
+
+impl Session {
+    fn load() -> Session {
+        let Some(path) = session_path() else {
+            return Session::default();
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                error!("Ignoring malformed session in {:?}: {}", path, e);
+                Session::default()
+            }),
+            Err(e) => {
+                debug!("No session loaded from {:?}: {}", path, e);
+                Session::default()
+            }
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = session_path() else {
+            error!("Cannot save session: no state directory");
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, serde_json::to_string(self).unwrap()));
+        if let Err(e) = result {
+            error!("Failed to save session to {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Usually ~/.local/state/russpot/session.json.
+fn session_path() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::cache_dir)
+        .map(|dir| dir.join("russpot").join("session.json"))
+}
+
+#[derive(Debug, Clone)]
 pub enum In {
     Nav(NavCommand),
     #[doc(hidden)]
     EnsureCurrentVisible,
+    #[doc(hidden)]
+    CursorMoved(SpotItem),
+    /// A page changed in a way the session keeps, e.g. the time range of the top page.
+    #[doc(hidden)]
+    PageChanged,
     /// Descend into selected playlist or album.
     NavDescend,
+    /// Open the details page of the track under cursor.
+    NavDetails,
     /// Move back up to the previews view.
     NavBack,
+    /// Reopen the page last left with NavBack.
+    NavForward,
+    /// Open the list of recently visited pages.
+    ShowHistory,
+    #[doc(hidden)]
+    JumpToHistory(usize),
+    /// Go back to the page at this depth of the stack, 0 being the root.
+    JumpToLevel(usize),
+    /// Open a new page for the collection, on top of the current one.
+    Push(SpotItem),
+    /// Like Push, with the cursor on the item with this uri once the list loads.
+    PushAt(SpotItem, String),
     /// Reset the view to saved playlists.
     NavResetPlaylists,
     /// Reset the view to search page.
     NavResetSearch,
+    /// Reset the view to search page and search, for one type of results if given.
+    Search(Option<SearchType>, String),
+    /// Reset the view to saved podcasts.
+    NavResetShows,
+    /// Reset the view to recently played tracks.
+    NavResetRecent,
+    /// Reset the view to top tracks and artists.
+    NavResetTop,
+    /// Reset the view to Connect devices.
+    NavResetDevices,
+    /// Switch the top tracks and artists to the next time range.
+    CycleTimeRange,
+    /// Re-sort or regroup the list under cursor, on whichever page is on top.
+    Sort(SortCommand),
+    /// Select items on the list under cursor, on whichever page is on top.
+    Select(SelectCommand),
+    /// Apply a library edit to all the pages in the stack.
+    Library(LibraryChange),
+    /// Show the devices again on all the pages in the stack, after the preferred one changed.
+    RefreshDevices,
 }
 
 #[derive(Debug)]
-pub enum Out {}
+pub enum Out {
+    /// The cursor moved onto the item, in whichever page is on top.
+    CursorIsNowAt(SpotItem),
+}
 
 #[derive(Debug)]
 pub enum CmdOut {}
@@ -53,11 +165,32 @@ impl relm4::Component for Model {
 
     view! {
         #[root]
-        gtk::Box::new(gtk::Orientation::Horizontal, 0) {
+        gtk::Box::new(gtk::Orientation::Vertical, 0) {
             set_vexpand: true,
             set_height_request: 400,
+            gtk::Box::new(gtk::Orientation::Horizontal, 0) {
+                #[name="breadcrumbs"]
+                gtk::Box::new(gtk::Orientation::Horizontal, 0) {
+                    set_css_classes: &["breadcrumbs"],
+                    set_hexpand: true,
+                },
+                #[name="history_button"]
+                gtk::MenuButton {
+                    set_label: "History",
+                    #[wrap(Some)]
+                    set_popover = &gtk::Popover {
+                        #[name="history_list"]
+                        gtk::ListBox {
+                            connect_row_activated[sender] => move |_, row| {
+                                sender.input(In::JumpToHistory(row.index() as usize));
+                            },
+                        },
+                    },
+                },
+            },
             #[name="scrollwin"]
             gtk::ScrolledWindow {
+                set_vexpand: true,
                 #[local_ref]
                 view_widgets -> gtk::Stack {
                     set_vexpand: true,
@@ -66,10 +199,6 @@ impl relm4::Component for Model {
         }
     }
 
-    fn post_view() {
-        view_widgets.set_visible_child(&view_widgets.last_child().unwrap());
-    }
-
     fn init(
         init: Self::Init,
         root: Self::Root,
@@ -78,7 +207,9 @@ impl relm4::Component for Model {
         let views = FactoryVecDeque::<Child>::builder()
             .launch(gtk::Stack::new())
             .forward(sender.input_sender(), move |out| match out {
-                ChildOut::Nav(NavOutput::CursorIsNowAt(_)) => In::EnsureCurrentVisible,
+                ChildOut::Nav(NavOutput::CursorIsNowAt(item)) => In::CursorMoved(item),
+                ChildOut::Push(item) => In::Push(item),
+                ChildOut::Changed => In::PageChanged,
                 // When the cursor attemts to leave a child view, bounce it back:
                 ChildOut::Nav(nav_out) => In::Nav(match nav_out {
                     NavOutput::EscapedUp => NavCommand::Down,
@@ -90,14 +221,32 @@ impl relm4::Component for Model {
             });
         let mut model = Model {
             views,
+            top: 0,
+            history: VecDeque::new(),
+            history_button: gtk::MenuButton::default(),
+            history_list: gtk::ListBox::default(),
+            breadcrumbs: gtk::Box::default(),
+            input: sender.input_sender().clone(),
             gtk_stack: gtk::Stack::default(),
             scrollwin: gtk::ScrolledWindow::new(),
+            session: Rc::default(),
         };
         let view_widgets = model.views.widget();
         let widgets = view_output!();
         model.gtk_stack = view_widgets.clone();
-        sender.input_sender().emit(In::NavResetPlaylists);
         model.scrollwin = widgets.scrollwin.clone();
+        model.history_button = widgets.history_button.clone();
+        model.history_list = widgets.history_list.clone();
+        model.breadcrumbs = widgets.breadcrumbs.clone();
+
+        let session = Session::load();
+        if session.pages.is_empty() {
+            sender.input_sender().emit(In::NavResetPlaylists);
+        } else {
+            model.restore(session);
+        }
+        let saved = model.session.clone();
+        relm4::main_application().connect_shutdown(move |_| saved.borrow().save());
         ComponentParts { model, widgets }
     }
 
@@ -106,61 +255,272 @@ impl relm4::Component for Model {
             In::EnsureCurrentVisible => {
                 self.ensure_current_visible();
             }
+            In::CursorMoved(item) => {
+                self.ensure_current_visible();
+                sender.output_sender().emit(Out::CursorIsNowAt(item));
+            }
+            // Only for the session, saved below.
+            In::PageChanged => {}
             In::Nav(nav_cmd) => {
-                self.views
-                    .guard()
-                    .back()
-                    .expect("page stack cannot be empty")
-                    .emit_nav(nav_cmd);
+                self.current_page().emit_nav(nav_cmd);
                 // Scroll the view so that the newly selected item is still visible.
                 // We're using a message here, instead of direct function call,
                 // so that the cursor has time to move before the calculation happens.
                 sender.input_sender().emit(In::EnsureCurrentVisible);
             }
             In::NavDescend => {
-                let mut pages = self.views.guard();
-                let maybe_dli = {
-                    let last_page = pages.back().expect("page stack cannot be empty");
-                    last_page.child.descend()
-                };
-                if let Some(dli) = maybe_dli {
+                if let Some(dli) = self.current_page().child.descend() {
                     debug!("descending into {:?}", dli);
-                    pages.push_back(ChildInit {
-                        layout: ChildLayout::SingleDenseList(dli.source),
-                    });
+                    self.open(ChildLayout::SingleDenseList(dli.source));
                 } else {
                     debug!("cannot descend");
                 }
             }
+            In::NavDetails => match self.current_page().child.current_item() {
+                Some(SpotItem::Track(ft)) => self.open(ChildLayout::TrackDetails(ft)),
+                other => debug!("no details page for {:?}", other),
+            },
+            In::Push(item) => {
+                debug!("pushing {:?}", item);
+                self.open(ChildLayout::SingleDenseList(item));
+            }
+            In::PushAt(item, uri) => {
+                debug!("pushing {:?} at {}", item, uri);
+                self.open_init(ChildInit {
+                    cursor: Some((0, uri)),
+                    ..ChildInit::new(ChildLayout::SingleDenseList(item))
+                });
+            }
             In::NavBack => {
-                let mut pages = self.views.guard();
-                if pages.len() == 1 {
+                if self.top == 0 {
                     debug!("cannot go back up: already at the root");
                 } else {
-                    pages.pop_back();
+                    self.switch_to(self.top - 1);
+                }
+            }
+            In::NavForward => {
+                if self.top + 1 < self.views.len() {
+                    self.switch_to(self.top + 1);
+                } else {
+                    debug!("cannot go forward: no page was left");
+                }
+            }
+            In::ShowHistory => self.history_button.popup(),
+            In::JumpToHistory(idx) => {
+                let Some(layout) = self.history.get(idx).cloned() else {
+                    return;
+                };
+                self.history_button.popdown();
+                // Pages still in the stack are reopened as they were left.
+                let key = layout.key();
+                match self
+                    .views
+                    .iter()
+                    .position(|page| page.init.layout.key() == key)
+                {
+                    Some(idx) => self.switch_to(idx),
+                    None => self.open(layout),
+                }
+            }
+            In::JumpToLevel(level) => {
+                if level < self.top {
+                    self.switch_to(level);
+                } else {
+                    debug!("cannot jump to level {}: already there", level);
                 }
             }
             In::NavResetPlaylists => {
-                let mut pages = self.views.guard();
-                pages.clear();
                 debug!("NavResetPlaylists");
-                pages.push_back(ChildInit {
-                    layout: ChildLayout::SingleDenseList(SpotItem::UserPlaylists),
-                });
+                self.reset(ChildLayout::SingleDenseList(SpotItem::UserPlaylists));
             }
             In::NavResetSearch => {
-                let mut pages = self.views.guard();
-                pages.clear();
                 debug!("NavResetSearch");
-                pages.push_back(ChildInit {
-                    layout: ChildLayout::SearchPage,
-                });
+                self.reset(ChildLayout::SearchPage);
+            }
+            In::Search(st, query) => {
+                self.reset(ChildLayout::SearchPage);
+                if let ChildContent::SearchPage(sp) = &self.current_page().child {
+                    sp.emit(searchpage::In::Search(st, query));
+                }
+            }
+            In::NavResetShows => {
+                debug!("NavResetShows");
+                self.reset(ChildLayout::SingleDenseList(SpotItem::SavedShows));
+            }
+            In::NavResetRecent => {
+                debug!("NavResetRecent");
+                self.reset(ChildLayout::SingleDenseList(SpotItem::RecentlyPlayed));
+            }
+            In::NavResetTop => {
+                debug!("NavResetTop");
+                self.reset(ChildLayout::TopPage);
+            }
+            In::NavResetDevices => {
+                debug!("NavResetDevices");
+                self.reset(ChildLayout::SingleDenseList(SpotItem::Devices));
+            }
+            In::CycleTimeRange => {
+                if let ChildContent::TopPage(tp) = &self.current_page().child {
+                    tp.emit(toppage::In::CycleRange);
+                }
+            }
+            In::Sort(cmd) => self.current_page().child.sort(cmd),
+            In::Select(cmd) => self.current_page().child.select(cmd),
+            In::Library(change) => {
+                for page in self.views.iter() {
+                    page.child.apply_library_change(change.clone());
+                }
+            }
+            In::RefreshDevices => {
+                for page in self.views.iter() {
+                    if let ChildContent::MultiView(mv) = &page.child {
+                        mv.emit(multiview::In::RefreshDevices);
+                    }
+                }
             }
         }
+        self.remember_session();
     }
 }
 
 impl Model {
+    fn current_page(&self) -> &Child {
+        self.views
+            .get(self.top)
+            .expect("page stack cannot be empty")
+    }
+
+    /// Opens a new page on top of the current one.
+    /// Like in a browser, this drops the pages left with NavBack.
+    fn open(&mut self, layout: ChildLayout) {
+        self.open_init(ChildInit::new(layout));
+    }
+
+    fn open_init(&mut self, init: ChildInit) {
+        self.save_scroll();
+        let layout = init.layout.clone();
+        {
+            let mut pages = self.views.guard();
+            while pages.len() > self.top + 1 {
+                pages.pop_back();
+            }
+            pages.push_back(init);
+        }
+        self.top = self.views.len() - 1;
+        self.show_top();
+        self.visited(layout);
+    }
+
+    /// Replaces the whole stack with a single page.
+    fn reset(&mut self, layout: ChildLayout) {
+        {
+            let mut pages = self.views.guard();
+            pages.clear();
+            pages.push_back(ChildInit::new(layout.clone()));
+        }
+        self.top = 0;
+        self.show_top();
+        self.visited(layout);
+    }
+
+    /// Rebuilds the stack saved by the previous run.
+    fn restore(&mut self, session: Session) {
+        debug!("restoring {} pages", session.pages.len());
+        {
+            let mut pages = self.views.guard();
+            for page in session.pages {
+                pages.push_back(page);
+            }
+        }
+        self.top = session.top.min(self.views.len() - 1);
+        for idx in 0..=self.top {
+            let layout = self.views.get(idx).unwrap().init.layout.clone();
+            self.visited(layout);
+        }
+        self.show_top();
+        self.remember_session();
+    }
+
+    fn remember_session(&self) {
+        *self.session.borrow_mut() = Session {
+            pages: self.views.iter().map(Child::saved).collect(),
+            top: self.top,
+        };
+    }
+
+    /// Shows another page already in the stack, scrolled to where it was left.
+    fn switch_to(&mut self, idx: usize) {
+        self.save_scroll();
+        self.top = idx;
+        self.show_top();
+        let page = self.current_page();
+        let scroll = page.scroll.get();
+        let layout = page.init.layout.clone();
+        // The adjustment only gets the size of the page on the next layout.
+        let adj = self.scrollwin.vadjustment();
+        gtk::glib::idle_add_local_once(move || adj.set_value(scroll));
+        self.visited(layout);
+    }
+
+    fn save_scroll(&self) {
+        if let Some(page) = self.views.get(self.top) {
+            page.scroll.set(self.scrollwin.vadjustment().value());
+        }
+    }
+
+    fn show_top(&self) {
+        let page = self
+            .gtk_stack
+            .observe_children()
+            .item(self.top as u32)
+            .and_downcast::<gtk::Widget>();
+        match page {
+            Some(page) => self.gtk_stack.set_visible_child(&page),
+            None => error!("page {} is not in the stack", self.top),
+        }
+        self.update_breadcrumbs();
+    }
+
+    /// Rebuilds the breadcrumbs, e.g. "Saved playlists › Chill Mix › Album X".
+    /// All but the last one, which is the page on screen, jump back to their page.
+    fn update_breadcrumbs(&self) {
+        while let Some(crumb) = self.breadcrumbs.first_child() {
+            self.breadcrumbs.remove(&crumb);
+        }
+        for (level, page) in self.views.iter().take(self.top + 1).enumerate() {
+            let title = page.init.layout.title();
+            if level == self.top {
+                let label = gtk::Label::new(Some(&title));
+                label.set_css_classes(&["current"]);
+                self.breadcrumbs.append(&label);
+                break;
+            }
+            let button = gtk::Button::with_label(&title);
+            button.set_has_frame(false);
+            if level < 9 {
+                button.set_tooltip_text(Some(&format!("Alt+{}", level + 1)));
+            }
+            let input = self.input.clone();
+            button.connect_clicked(move |_| input.emit(In::JumpToLevel(level)));
+            self.breadcrumbs.append(&button);
+            self.breadcrumbs.append(&gtk::Label::new(Some("›")));
+        }
+    }
+
+    /// Moves the page to the front of the history.
+    fn visited(&mut self, layout: ChildLayout) {
+        let key = layout.key();
+        self.history.retain(|old| old.key() != key);
+        self.history.push_front(layout);
+        self.history.truncate(HISTORY_LEN);
+        self.history_list.remove_all();
+        for layout in &self.history {
+            let label = gtk::Label::new(Some(&layout.title()));
+            label.set_xalign(0.0);
+            self.history_list.append(&label);
+        }
+    }
+
     fn last_page_widget(&self) -> gtk::Widget {
         self.gtk_stack.last_child().unwrap()
     }
@@ -169,7 +529,7 @@ impl Model {
     /// Returns the delta in pixels.
     /// Returns None if no scrollng was performed.
     fn ensure_current_visible(&self) -> Option<f64> {
-        let widget = self.views.back()?.child_widget();
+        let widget = self.views.get(self.top)?.child_widget();
 
         let point = widget.compute_point(&self.scrollwin, &gtk::graphene::Point::new(0.0, 0.0))?;
         let mut delta: f64 = 0.0;
@@ -193,8 +553,34 @@ impl Model {
         }
     }
 
-    pub fn play_context(&self) -> Option<(PlayContextId<'static>, Option<Offset>)> {
-        self.views.back()?.child.play_context()
+    pub fn play_context(&self) -> Option<PlayTarget> {
+        self.views.get(self.top)?.child.play_context()
+    }
+
+    /// The item under cursor on the current page.
+    pub fn current_item(&self) -> Option<SpotItem> {
+        self.views.get(self.top)?.child.current_item()
+    }
+
+    /// The collection displayed around the cursor on the current page.
+    pub fn current_source(&self) -> Option<SpotItem> {
+        self.views.get(self.top)?.child.current_source()
+    }
+
+    /// All the items of the list around the cursor on the current page.
+    pub fn current_items(&self) -> Vec<SpotItem> {
+        self.views
+            .get(self.top)
+            .map(|page| page.child.current_items())
+            .unwrap_or_default()
+    }
+
+    /// The items selected on the current page, or the item under cursor if none are.
+    pub fn selection(&self) -> Vec<SpotItem> {
+        self.views
+            .get(self.top)
+            .map(|page| page.child.selection())
+            .unwrap_or_default()
     }
 }
 
@@ -202,6 +588,8 @@ impl Model {
 pub enum ChildContent {
     MultiView(Controller<multiview::Model>),
     SearchPage(Controller<searchpage::Model>),
+    TopPage(Controller<toppage::Model>),
+    TrackDetails(Controller<trackdetails::Model>),
 }
 
 impl ChildContent {
@@ -209,13 +597,102 @@ impl ChildContent {
         match self {
             ChildContent::MultiView(mv) => mv.model().descend(),
             ChildContent::SearchPage(sp) => sp.model().descend(),
+            ChildContent::TopPage(tp) => tp.model().descend(),
+            ChildContent::TrackDetails(td) => td
+                .model()
+                .descend()
+                .map(|source| denselist::Init { source }),
         }
     }
 
-    pub fn play_context(&self) -> Option<(PlayContextId<'static>, Option<Offset>)> {
+    pub fn play_context(&self) -> Option<PlayTarget> {
         match self {
             ChildContent::MultiView(mv) => mv.model().play_context(),
             ChildContent::SearchPage(sp) => sp.model().play_context(),
+            ChildContent::TopPage(tp) => tp.model().play_context(),
+            ChildContent::TrackDetails(td) => td.model().play_context(),
+        }
+    }
+
+    fn current_item(&self) -> Option<SpotItem> {
+        match self {
+            ChildContent::MultiView(mv) => mv.model().current_item(),
+            ChildContent::SearchPage(sp) => sp.model().current_item(),
+            ChildContent::TopPage(tp) => tp.model().current_item(),
+            ChildContent::TrackDetails(td) => td.model().current_item(),
+        }
+    }
+
+    fn current_source(&self) -> Option<SpotItem> {
+        match self {
+            ChildContent::MultiView(mv) => mv.model().current_source(),
+            ChildContent::SearchPage(sp) => sp.model().current_source(),
+            ChildContent::TopPage(tp) => tp.model().current_source(),
+            ChildContent::TrackDetails(_) => None,
+        }
+    }
+
+    fn current_items(&self) -> Vec<SpotItem> {
+        match self {
+            ChildContent::MultiView(mv) => mv.model().current_items(),
+            ChildContent::SearchPage(sp) => sp.model().current_items(),
+            ChildContent::TopPage(tp) => tp.model().current_items(),
+            ChildContent::TrackDetails(_) => vec![],
+        }
+    }
+
+    /// Section and uri of the item under cursor.
+    fn cursor(&self) -> Option<(usize, String)> {
+        match self {
+            ChildContent::MultiView(mv) => mv.model().cursor(),
+            ChildContent::SearchPage(sp) => sp.model().cursor(),
+            ChildContent::TopPage(tp) => tp.model().cursor(),
+            ChildContent::TrackDetails(_) => None,
+        }
+    }
+
+    fn restore_cursor(&self, section: usize, uri: String) {
+        match self {
+            ChildContent::MultiView(mv) => mv.emit(multiview::In::RestoreCursor(section, uri)),
+            ChildContent::SearchPage(sp) => sp.emit(searchpage::In::RestoreCursor(section, uri)),
+            ChildContent::TopPage(tp) => tp.emit(toppage::In::RestoreCursor(section, uri)),
+            ChildContent::TrackDetails(_) => {}
+        }
+    }
+
+    fn sort(&self, cmd: SortCommand) {
+        match self {
+            ChildContent::MultiView(mv) => mv.emit(multiview::In::Sort(cmd)),
+            ChildContent::SearchPage(sp) => sp.emit(searchpage::In::Sort(cmd)),
+            ChildContent::TopPage(tp) => tp.emit(toppage::In::Sort(cmd)),
+            ChildContent::TrackDetails(_) => {}
+        }
+    }
+
+    fn select(&self, cmd: SelectCommand) {
+        match self {
+            ChildContent::MultiView(mv) => mv.emit(multiview::In::Select(cmd)),
+            ChildContent::SearchPage(sp) => sp.emit(searchpage::In::Select(cmd)),
+            ChildContent::TopPage(tp) => tp.emit(toppage::In::Select(cmd)),
+            ChildContent::TrackDetails(_) => {}
+        }
+    }
+
+    fn selection(&self) -> Vec<SpotItem> {
+        match self {
+            ChildContent::MultiView(mv) => mv.model().selection(),
+            ChildContent::SearchPage(sp) => sp.model().selection(),
+            ChildContent::TopPage(tp) => tp.model().selection(),
+            ChildContent::TrackDetails(td) => td.model().current_item().into_iter().collect(),
+        }
+    }
+
+    fn apply_library_change(&self, change: LibraryChange) {
+        match self {
+            ChildContent::MultiView(mv) => mv.emit(multiview::In::Library(change)),
+            ChildContent::SearchPage(sp) => sp.emit(searchpage::In::Library(change)),
+            ChildContent::TopPage(tp) => tp.emit(toppage::In::Library(change)),
+            ChildContent::TrackDetails(_) => {}
         }
     }
 }
@@ -224,17 +701,69 @@ impl ChildContent {
 pub struct Child {
     init: ChildInit,
     child: ChildContent,
+    /// Where the page was scrolled to when another one was shown.
+    scroll: Cell<f64>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ChildLayout {
     SingleDenseList(SpotItem),
     SearchPage,
+    TopPage,
+    TrackDetails(FullTrack),
 }
 
-#[derive(Debug)]
+impl ChildLayout {
+    pub fn title(&self) -> String {
+        match self {
+            ChildLayout::SingleDenseList(source) => source.name(),
+            ChildLayout::SearchPage => "Search".to_string(),
+            ChildLayout::TopPage => "Top".to_string(),
+            ChildLayout::TrackDetails(ft) => format!("{} details", ft.name),
+        }
+    }
+
+    /// Identifies the page, to find it again in the stack.
+    fn key(&self) -> String {
+        match self {
+            ChildLayout::SingleDenseList(source) => source.uri().unwrap_or_else(|| source.name()),
+            ChildLayout::SearchPage => "search".to_string(),
+            ChildLayout::TopPage => "top".to_string(),
+            ChildLayout::TrackDetails(ft) => format!(
+                "details:{}",
+                ft.id.as_ref().map(|id| id.uri()).unwrap_or_default()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChildInit {
     layout: ChildLayout,
+    /// Section and uri of the item to put the cursor on once it loads.
+    #[serde(default)]
+    cursor: Option<(usize, String)>,
+    /// Query to run on a search page.
+    #[serde(default)]
+    query: Option<String>,
+    /// The only type of results the query was run for, if just one.
+    #[serde(default)]
+    search_type: Option<SearchType>,
+    /// Time range of a top page.
+    #[serde(default)]
+    range: Option<TimeRange>,
+}
+
+impl ChildInit {
+    fn new(layout: ChildLayout) -> ChildInit {
+        ChildInit {
+            layout,
+            cursor: None,
+            query: None,
+            search_type: None,
+            range: None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -251,6 +780,10 @@ impl From<NavCommand> for ChildIn {
 #[derive(Debug)]
 pub enum ChildOut {
     Nav(NavOutput),
+    /// Open a page for the collection, e.g. from a link on the page.
+    Push(SpotItem),
+    /// The page changed in a way the session keeps.
+    Changed,
 }
 
 #[relm4::factory(pub)]
@@ -266,16 +799,13 @@ impl FactoryComponent for Child {
         gtk::Box::new(gtk::Orientation::Vertical, 0) {
             set_homogeneous: false,
             set_vexpand: true,
-            gtk::Label {
-                set_label: "Switch view item"
-            },
             self.child_widget() {
             },
         }
     }
 
     fn init_model(init: Self::Init, index: &Self::Index, sender: FactorySender<Self>) -> Self {
-        match init.layout {
+        let child = match init.layout {
             ChildLayout::SingleDenseList(ref source) => {
                 let mv = multiview::Model::builder()
                     .launch(multiview::Init {
@@ -286,10 +816,7 @@ impl FactoryComponent for Child {
                     .forward(sender.output_sender(), |msg| match msg {
                         multiview::Out::Nav(nav_out) => ChildOut::Nav(nav_out),
                     });
-                Child {
-                    init,
-                    child: ChildContent::MultiView(mv),
-                }
+                ChildContent::MultiView(mv)
             }
             ChildLayout::SearchPage => {
                 let sp = searchpage::Model::builder().launch(()).forward(
@@ -298,11 +825,39 @@ impl FactoryComponent for Child {
                         searchpage::Out::Nav(nav_out) => ChildOut::Nav(nav_out),
                     },
                 );
-                Child {
-                    init,
-                    child: ChildContent::SearchPage(sp),
-                }
+                ChildContent::SearchPage(sp)
+            }
+            ChildLayout::TopPage => {
+                let tp = toppage::Model::builder().launch(init.range).forward(
+                    sender.output_sender(),
+                    |msg| match msg {
+                        toppage::Out::Nav(nav_out) => ChildOut::Nav(nav_out),
+                        toppage::Out::RangeChanged(_) => ChildOut::Changed,
+                    },
+                );
+                ChildContent::TopPage(tp)
+            }
+            ChildLayout::TrackDetails(ref ft) => {
+                let td = trackdetails::Model::builder().launch(ft.clone()).forward(
+                    sender.output_sender(),
+                    |msg| match msg {
+                        trackdetails::Out::Nav(nav_out) => ChildOut::Nav(nav_out),
+                        trackdetails::Out::Open(item) => ChildOut::Push(item),
+                    },
+                );
+                ChildContent::TrackDetails(td)
             }
+        };
+        if let (Some(query), ChildContent::SearchPage(sp)) = (&init.query, &child) {
+            sp.emit(searchpage::In::Search(init.search_type, query.clone()));
+        }
+        if let Some((section, uri)) = &init.cursor {
+            child.restore_cursor(*section, uri.clone());
+        }
+        Child {
+            init,
+            child,
+            scroll: Cell::new(0.0),
         }
     }
 
@@ -314,10 +869,34 @@ impl FactoryComponent for Child {
 }
 
 impl Child {
+    /// How to bring the page back as it is now.
+    /// Lists that did not load yet keep the cursor they were restored with.
+    fn saved(&self) -> ChildInit {
+        let (query, search_type) = match &self.child {
+            ChildContent::SearchPage(sp) if sp.model().query().is_some() => {
+                (sp.model().query(), sp.model().search_type())
+            }
+            _ => (self.init.query.clone(), self.init.search_type),
+        };
+        let range = match &self.child {
+            ChildContent::TopPage(tp) => Some(tp.model().range()),
+            _ => None,
+        };
+        ChildInit {
+            layout: self.init.layout.clone(),
+            cursor: self.child.cursor().or_else(|| self.init.cursor.clone()),
+            query,
+            search_type,
+            range,
+        }
+    }
+
     fn emit_nav(&self, nav_cmd: NavCommand) {
         match &self.child {
             ChildContent::MultiView(mv) => mv.emit(multiview::In::Nav(nav_cmd)),
             ChildContent::SearchPage(sp) => sp.emit(searchpage::In::Nav(nav_cmd)),
+            ChildContent::TopPage(tp) => tp.emit(toppage::In::Nav(nav_cmd)),
+            ChildContent::TrackDetails(td) => td.emit(trackdetails::In::Nav(nav_cmd)),
         }
     }
 
@@ -325,6 +904,8 @@ impl Child {
         match &self.child {
             ChildContent::MultiView(mv) => mv.widget().clone().into(),
             ChildContent::SearchPage(sp) => sp.widget().clone().into(),
+            ChildContent::TopPage(tp) => tp.widget().clone().into(),
+            ChildContent::TrackDetails(td) => td.widget().clone().into(),
         }
     }
 }
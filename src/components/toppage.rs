@@ -0,0 +1,180 @@
+//! Top page shows the most listened songs and artists of the user,
+//! over a selectable time range.
+
+use gtk::prelude::*;
+
+use relm4::prelude::*;
+use rspotify::model::TimeRange;
+
+use crate::{
+    components::{
+        denselist::{self, SelectCommand},
+        listorder::SortCommand,
+        multiview,
+    },
+    navigation::{NavCommand, NavOutput},
+    spotconn::model::{LibraryChange, PlayTarget, SpotItem},
+};
+
+#[derive(Debug)]
+pub struct Model {
+    range: TimeRange,
+    multiview: Controller<multiview::Model>,
+}
+
+#[derive(Debug)]
+pub enum In {
+    SetRange(TimeRange),
+    /// Switch to the next time range: short, medium, long, short again.
+    CycleRange,
+    RestoreCursor(usize, String),
+    Nav(NavCommand),
+    Library(LibraryChange),
+    Sort(SortCommand),
+    Select(SelectCommand),
+}
+
+#[derive(Debug)]
+pub enum Out {
+    Nav(NavOutput),
+    /// The time range changed, so that the session keeps the new one.
+    RangeChanged(TimeRange),
+}
+
+#[relm4::component(pub)]
+impl Component for Model {
+    /// The time range to start with, the last 6 months if none.
+    type Init = Option<TimeRange>;
+    type Input = In;
+    type Output = Out;
+    type CommandOutput = ();
+
+    view! {
+        #[root]
+        gtk::Box::new(gtk::Orientation::Vertical, 0) {
+            set_hexpand: true,
+
+            gtk::Box::new(gtk::Orientation::Horizontal, 0) {
+                set_css_classes: &["linked"],
+                gtk::ToggleButton {
+                    set_label: "4 weeks",
+                    #[watch]
+                    set_active: model.range == TimeRange::ShortTerm,
+                    connect_clicked => In::SetRange(TimeRange::ShortTerm),
+                },
+                gtk::ToggleButton {
+                    set_label: "6 months",
+                    #[watch]
+                    set_active: model.range == TimeRange::MediumTerm,
+                    connect_clicked => In::SetRange(TimeRange::MediumTerm),
+                },
+                gtk::ToggleButton {
+                    set_label: "All time",
+                    #[watch]
+                    set_active: model.range == TimeRange::LongTerm,
+                    connect_clicked => In::SetRange(TimeRange::LongTerm),
+                },
+            },
+
+            #[local_ref]
+            multiview_widget -> gtk::Box {},
+        },
+    }
+
+    fn init(
+        init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let range = init.unwrap_or(TimeRange::MediumTerm);
+        let multiview = multiview::Model::builder()
+            .launch(multiview::Init {
+                sections: Model::sections(range),
+            })
+            .forward(sender.output_sender(), |msg| match msg {
+                multiview::Out::Nav(nav_out) => Out::Nav(nav_out),
+            });
+
+        let model = Model { range, multiview };
+        let multiview_widget = model.multiview.widget();
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
+        match message {
+            In::SetRange(range) => self.set_range(range, &sender),
+            In::CycleRange => {
+                let range = match self.range {
+                    TimeRange::ShortTerm => TimeRange::MediumTerm,
+                    TimeRange::MediumTerm => TimeRange::LongTerm,
+                    TimeRange::LongTerm => TimeRange::ShortTerm,
+                };
+                self.set_range(range, &sender);
+            }
+            In::RestoreCursor(section, uri) => self
+                .multiview
+                .emit(multiview::In::RestoreCursor(section, uri)),
+            In::Nav(nav_cmd) => self.multiview.emit(multiview::In::Nav(nav_cmd)),
+            In::Library(change) => self.multiview.emit(multiview::In::Library(change)),
+            In::Sort(cmd) => self.multiview.emit(multiview::In::Sort(cmd)),
+            In::Select(cmd) => self.multiview.emit(multiview::In::Select(cmd)),
+        }
+    }
+}
+
+impl Model {
+    fn sections(range: TimeRange) -> Vec<denselist::Init> {
+        vec![
+            denselist::Init {
+                source: SpotItem::TopTracks(range),
+            },
+            denselist::Init {
+                source: SpotItem::TopArtists(range),
+            },
+        ]
+    }
+
+    fn set_range(&mut self, range: TimeRange, sender: &ComponentSender<Self>) {
+        if range == self.range {
+            return;
+        }
+        self.range = range;
+        self.multiview
+            .emit(multiview::In::ResetSections(Model::sections(range)));
+        sender.output_sender().emit(Out::RangeChanged(range));
+    }
+
+    pub fn range(&self) -> TimeRange {
+        self.range
+    }
+
+    pub fn descend(&self) -> Option<denselist::Init> {
+        self.multiview.model().descend()
+    }
+
+    pub fn play_context(&self) -> Option<PlayTarget> {
+        self.multiview.model().play_context()
+    }
+
+    pub fn current_item(&self) -> Option<SpotItem> {
+        self.multiview.model().current_item()
+    }
+
+    pub fn current_source(&self) -> Option<SpotItem> {
+        self.multiview.model().current_source()
+    }
+
+    pub fn current_items(&self) -> Vec<SpotItem> {
+        self.multiview.model().current_items()
+    }
+
+    pub fn selection(&self) -> Vec<SpotItem> {
+        self.multiview.model().selection()
+    }
+
+    pub fn cursor(&self) -> Option<(usize, String)> {
+        self.multiview.model().cursor()
+    }
+}
@@ -0,0 +1,260 @@
+//! Track details page: everything Spotify tells about a single track,
+//! with links on to its album and artists.
+
+use gtk::prelude::*;
+use log::{debug, error};
+use relm4::prelude::*;
+use rspotify::model::FullTrack;
+
+use crate::navigation::{NavCommand, NavOutput};
+use crate::offline::Offline;
+use crate::spotconn::model::{format_duration, format_key, PlayTarget, SpotItem, TrackDetails};
+use crate::spotconn::SpotConn;
+
+use super::smallblock;
+
+#[derive(Debug)]
+pub struct Model {
+    track: FullTrack,
+    grid: gtk::Grid,
+    /// Number of rows in the grid so far.
+    rows: i32,
+    links_box: gtk::Box,
+    /// The album, then the artists once they are loaded.
+    links: Vec<Link>,
+    cursor: Option<usize>,
+    /// Shown until the details are loaded, or instead of them if that failed.
+    status: Option<String>,
+}
+
+/// A smallblock leading on to another page.
+#[derive(Debug)]
+struct Link {
+    /// Carries the has-cursor class.
+    frame: gtk::Box,
+    block: Controller<smallblock::Model>,
+}
+
+#[derive(Debug)]
+pub enum In {
+    Nav(NavCommand),
+    #[doc(hidden)]
+    OpenLink(usize),
+}
+
+#[derive(Debug)]
+pub enum Out {
+    Nav(NavOutput),
+    /// Open a page for the album or artist.
+    Open(SpotItem),
+}
+
+#[derive(Debug)]
+pub enum CmdOut {
+    Loaded(Result<TrackDetails, String>),
+}
+
+#[relm4::component(pub)]
+impl Component for Model {
+    type Init = FullTrack;
+    type Input = In;
+    type Output = Out;
+    type CommandOutput = CmdOut;
+
+    view! {
+        #[root]
+        gtk::Box::new(gtk::Orientation::Vertical, 0) {
+            set_css_classes: &["details"],
+            set_hexpand: true,
+
+            gtk::Label {
+                set_css_classes: &["title"],
+                set_label: &model.track.name,
+                set_xalign: 0.0,
+            },
+            gtk::Label {
+                set_label: &SpotItem::Track(model.track.clone()).artist(),
+                set_xalign: 0.0,
+            },
+            #[name="grid"]
+            gtk::Grid {
+                set_column_spacing: 12,
+                set_row_spacing: 4,
+            },
+            gtk::Label {
+                set_css_classes: &["offline"],
+                set_xalign: 0.0,
+                #[watch]
+                set_label: model.status.as_deref().unwrap_or_default(),
+                #[watch]
+                set_visible: model.status.is_some(),
+            },
+            #[name="links_box"]
+            gtk::Box::new(gtk::Orientation::Vertical, 0) {},
+        }
+    }
+
+    fn init(
+        track: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let mut model = Model {
+            track,
+            grid: gtk::Grid::default(),
+            rows: 0,
+            links_box: gtk::Box::default(),
+            links: vec![],
+            cursor: None,
+            status: Some("Loading details…".to_string()),
+        };
+        let widgets = view_output!();
+        model.grid = widgets.grid.clone();
+        model.links_box = widgets.links_box.clone();
+
+        let track = &model.track;
+        let rows = [
+            ("Duration", format_duration(track.duration)),
+            ("Popularity", format!("{} / 100", track.popularity)),
+            (
+                "Explicit",
+                if track.explicit { "yes" } else { "no" }.to_string(),
+            ),
+            (
+                "ISRC",
+                track.external_ids.get("isrc").cloned().unwrap_or_default(),
+            ),
+            (
+                "Disc / track",
+                format!("{} / {}", track.disc_number, track.track_number),
+            ),
+            (
+                "Released",
+                track.album.release_date.clone().unwrap_or_default(),
+            ),
+        ];
+        for (name, value) in rows {
+            model.add_row(name, &value);
+        }
+        model.add_link(SpotItem::Album(model.track.album.clone()), &sender);
+
+        let track = model.track.clone();
+        sender.oneshot_command(async move {
+            let details = SpotConn::global().track_details(&track).await;
+            Offline::global().observe(&details);
+            CmdOut::Loaded(details.map_err(|e| format!("{:?}", e)))
+        });
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
+        match message {
+            In::Nav(NavCommand::ClearCursor) => self.set_cursor(None, &sender),
+            In::Nav(NavCommand::Up) | In::Nav(NavCommand::Left) => {
+                let prev = self.cursor.map_or(0, |c| c.saturating_sub(1));
+                self.set_cursor(Some(prev), &sender);
+            }
+            In::Nav(NavCommand::Down) | In::Nav(NavCommand::Right) => {
+                let next = self.cursor.map_or(0, |c| c + 1);
+                self.set_cursor(Some(next.min(self.links.len() - 1)), &sender);
+            }
+            In::OpenLink(idx) => {
+                let item = self.links[idx].block.model().get_content().clone();
+                sender.output_sender().emit(Out::Open(item));
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            CmdOut::Loaded(Ok(details)) => {
+                self.status = None;
+                if let Some(label) = &details.label {
+                    self.add_row("Label", label);
+                }
+                match &details.features {
+                    Some(f) => {
+                        self.add_row("Tempo", &format!("{:.0} BPM", f.tempo));
+                        self.add_row("Key", &format_key(f.key, f.mode));
+                        self.add_row("Energy", &format!("{:.0}%", f.energy * 100.0));
+                        self.add_row("Danceability", &format!("{:.0}%", f.danceability * 100.0));
+                    }
+                    None => debug!("no audio features for {}", self.track.name),
+                }
+                for artist in details.artists {
+                    self.add_link(SpotItem::Artist(artist), &sender);
+                }
+            }
+            CmdOut::Loaded(Err(e)) => {
+                error!("Could not load details of {}: {}", self.track.name, e);
+                self.status = Some("Could not load more details".to_string());
+            }
+        }
+    }
+}
+
+impl Model {
+    fn add_row(&mut self, name: &str, value: &str) {
+        let name = gtk::Label::new(Some(name));
+        name.set_xalign(0.0);
+        name.set_css_classes(&["dim"]);
+        let value = gtk::Label::new(Some(value));
+        value.set_xalign(0.0);
+        value.set_selectable(true);
+        self.grid.attach(&name, 0, self.rows, 1, 1);
+        self.grid.attach(&value, 1, self.rows, 1, 1);
+        self.rows += 1;
+    }
+
+    fn add_link(&mut self, item: SpotItem, sender: &ComponentSender<Self>) {
+        let idx = self.links.len();
+        let block = smallblock::Model::builder().launch(item.into()).forward(
+            sender.input_sender(),
+            move |msg| match msg {
+                smallblock::Out::Clicked => In::OpenLink(idx),
+            },
+        );
+        let frame = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        frame.append(block.widget());
+        self.links_box.append(&frame);
+        self.links.push(Link { frame, block });
+    }
+
+    fn set_cursor(&mut self, cursor: Option<usize>, sender: &ComponentSender<Self>) {
+        if let Some(link) = self.cursor.and_then(|c| self.links.get(c)) {
+            link.frame.remove_css_class("has-cursor");
+        }
+        self.cursor = cursor;
+        if let Some(link) = cursor.and_then(|c| self.links.get(c)) {
+            link.frame.add_css_class("has-cursor");
+            link.block.widget().grab_focus();
+            let item = link.block.model().get_content().clone();
+            sender
+                .output_sender()
+                .emit(Out::Nav(NavOutput::CursorIsNowAt(item)));
+        }
+    }
+
+    /// The link under cursor, opened with NavDescend.
+    pub fn descend(&self) -> Option<SpotItem> {
+        let link = self.links.get(self.cursor?)?;
+        Some(link.block.model().get_content().clone())
+    }
+
+    /// Plays the link under cursor, or the track on its own.
+    pub fn play_context(&self) -> Option<PlayTarget> {
+        PlayTarget::from_uri(&self.current_item()?.uri()?, None)
+    }
+
+    /// The link under cursor, or the track itself.
+    pub fn current_item(&self) -> Option<SpotItem> {
+        self.descend()
+            .or_else(|| Some(SpotItem::Track(self.track.clone())))
+    }
+}
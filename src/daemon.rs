@@ -0,0 +1,204 @@
+//! Headless Spotify Connect receiver: the russpot player without the GUI.
+//!
+//! Settings are read from daemon.json in the russpot config directory
+//! (usually ~/.config/russpot/daemon.json), and can be overridden by flags.
+//! Credentials may also come from RUSSPOT_USERNAME and RUSSPOT_PASSWORD env vars.
+//! Player events are logged, and passed to the on_event hook if one is configured.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use librespot::playback::player::PlayerEvent;
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+use tokio::signal::unix::{signal, Signal, SignalKind};
+
+use russpot::hooks::Hooks;
+use russpot::settings::config_path;
+use russpot::spotconn::librespot::{LibreSpotConfig, LibreSpotConn, LibreSpotTasks};
+
+/// Delay before the first reconnection attempt, doubled on every failed one.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+/// A connection that lasted this long is considered healthy, and resets the backoff.
+const STABLE_CONNECTION: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Parser)]
+#[command(name = "russpot-daemon", about = "Headless Spotify Connect receiver")]
+struct Args {
+    /// Config file (JSON). Defaults to daemon.json in the russpot config directory.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Name of the player in Connect device lists.
+    #[arg(long)]
+    name: Option<String>,
+    /// Audio backend, e.g. pulseaudio or alsa.
+    #[arg(long)]
+    backend: Option<String>,
+    /// Output device of the audio backend.
+    #[arg(long)]
+    device: Option<String>,
+    /// Streaming bitrate in kbps: 96, 160 or 320.
+    #[arg(long)]
+    bitrate: Option<String>,
+    #[arg(long)]
+    username: Option<String>,
+    /// Shell command to run on player events, with the details in env vars.
+    #[arg(long)]
+    on_event: Option<String>,
+}
+
+/// Contents of daemon.json: the connection settings, and the hook.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct DaemonConfig {
+    #[serde(flatten)]
+    librespot: LibreSpotConfig,
+    on_event: Option<String>,
+}
+
+enum Outcome {
+    Shutdown,
+    Disconnected,
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    let config = load_config(args);
+    let mut sigterm = signal(SignalKind::terminate()).expect("cannot listen for SIGTERM");
+
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        info!("Connecting as {:?}...", config.librespot.device_name);
+        let connected = tokio::select! {
+            connected = LibreSpotConn::connect(config.librespot.clone()) => connected,
+            _ = terminated(&mut sigterm) => return,
+        };
+        match connected {
+            Ok((conn, tasks)) => {
+                info!("Connected, waiting for Connect clients");
+                let t0 = Instant::now();
+                let hooks = config
+                    .on_event
+                    .clone()
+                    .map(|command| Hooks::start(command, conn.session.clone()));
+                match run(conn, tasks, hooks.as_ref(), &mut sigterm).await {
+                    Outcome::Shutdown => return,
+                    Outcome::Disconnected => {
+                        warn!("Session dropped");
+                        if let Some(hooks) = hooks {
+                            hooks.session_lost();
+                        }
+                    }
+                }
+                if t0.elapsed() >= STABLE_CONNECTION {
+                    backoff = MIN_BACKOFF;
+                }
+            }
+            Err(e) => error!("Connection failed: {}", e),
+        }
+        info!("Reconnecting in {}s", backoff.as_secs());
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {},
+            _ = terminated(&mut sigterm) => return,
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Merges the config file, env vars and flags, in increasing priority.
+fn load_config(args: Args) -> DaemonConfig {
+    let explicit = args.config.is_some();
+    let path = args.config.or_else(|| config_path("daemon.json"));
+    let mut config = match path.as_ref().map(std::fs::read_to_string) {
+        Some(Ok(data)) => serde_json::from_str(&data).unwrap_or_else(|e| {
+            error!("Malformed config in {:?}: {}", path, e);
+            std::process::exit(1);
+        }),
+        Some(Err(e)) if explicit => {
+            error!("Cannot read config {:?}: {}", path, e);
+            std::process::exit(1);
+        }
+        _ => {
+            debug!("No config loaded from {:?}, using defaults", path);
+            DaemonConfig::default()
+        }
+    };
+    let mut librespot = config.librespot.with_env_credentials();
+    if let Some(name) = args.name {
+        librespot.device_name = name;
+    }
+    librespot.backend = args.backend.or(librespot.backend);
+    librespot.audio_device = args.device.or(librespot.audio_device);
+    librespot.bitrate = args.bitrate.or(librespot.bitrate);
+    librespot.username = args.username.or(librespot.username);
+    config.librespot = librespot;
+    config.on_event = args.on_event.or(config.on_event);
+    config
+}
+
+/// Drives the connection until the session drops or the daemon is asked to stop.
+async fn run(
+    conn: LibreSpotConn,
+    tasks: LibreSpotTasks,
+    hooks: Option<&Hooks>,
+    sigterm: &mut Signal,
+) -> Outcome {
+    let LibreSpotTasks {
+        mut spirc_task,
+        mut player_events,
+    } = tasks;
+    loop {
+        tokio::select! {
+            _ = &mut spirc_task => return Outcome::Disconnected,
+            Some(event) = player_events.recv() => {
+                log_event(&event);
+                if let Some(hooks) = hooks {
+                    hooks.player_event(event);
+                }
+            }
+            _ = terminated(sigterm) => {
+                info!("Shutting down");
+                if let Some(spirc) = &conn.spirc {
+                    spirc.shutdown();
+                }
+                // Give spirc a moment to tell the other Connect devices we are gone.
+                if tokio::time::timeout(Duration::from_secs(5), spirc_task).await.is_err() {
+                    warn!("Spirc did not stop in time");
+                }
+                return Outcome::Shutdown;
+            }
+        }
+    }
+}
+
+/// Completes on SIGTERM or Ctrl-C.
+async fn terminated(sigterm: &mut Signal) {
+    tokio::select! {
+        _ = sigterm.recv() => {},
+        _ = tokio::signal::ctrl_c() => {},
+    }
+}
+
+fn log_event(event: &PlayerEvent) {
+    match event {
+        PlayerEvent::Playing {
+            track_id,
+            position_ms,
+            ..
+        } => info!("Playing {:?} from {}ms", track_id, position_ms),
+        PlayerEvent::Paused {
+            track_id,
+            position_ms,
+            ..
+        } => info!("Paused {:?} at {}ms", track_id, position_ms),
+        PlayerEvent::Stopped { track_id, .. } => info!("Stopped {:?}", track_id),
+        PlayerEvent::EndOfTrack { track_id, .. } => info!("Finished {:?}", track_id),
+        PlayerEvent::Unavailable { track_id, .. } => warn!("{:?} is unavailable", track_id),
+        PlayerEvent::VolumeSet { volume } => info!("Volume set to {}", volume),
+        other => debug!("Player event: {:?}", other),
+    }
+}
@@ -0,0 +1,193 @@
+//! User hooks: a shell command run on playback events, like spotifyd's `on_song_change_hook`.
+//!
+//! The command runs via `sh -c`, with the event described in env vars:
+//! - PLAYER_EVENT: change, play, pause, stop, volume_set or session_lost.
+//! - TRACK_ID and TRACK_URI, for all but volume_set and session_lost, and OLD_TRACK_ID on change.
+//! - TRACK_NAME, ARTISTS (comma separated) and DURATION_MS, when the metadata could be loaded.
+//! - POSITION_MS on play and pause, VOLUME (0 to 65535) on volume_set.
+
+use librespot::core::session::Session;
+use librespot::core::spotify_id::{SpotifyAudioType, SpotifyId};
+use librespot::metadata::{Artist, Episode, Metadata, Show, Track};
+use librespot::playback::player::{PlayerEvent, PlayerEventChannel};
+use log::{debug, error, warn};
+use tokio::sync::mpsc;
+
+enum HookEvent {
+    Player(PlayerEvent),
+    SessionLost,
+}
+
+/// Handle to the hook runner. Events are handled one by one, in order.
+#[derive(Clone)]
+pub struct Hooks {
+    queue: mpsc::UnboundedSender<HookEvent>,
+}
+
+impl Hooks {
+    /// Starts running the command for the events of the session.
+    /// Must be called from within the tokio runtime.
+    pub fn start(command: String, session: Session) -> Hooks {
+        let (queue, events) = mpsc::unbounded_channel();
+        tokio::spawn(work(command, session, events));
+        Hooks { queue }
+    }
+
+    pub fn player_event(&self, event: PlayerEvent) {
+        let _ = self.queue.send(HookEvent::Player(event));
+    }
+
+    /// Passes on all the events from the channel, until it closes.
+    pub fn forward(&self, mut events: PlayerEventChannel) {
+        let hooks = self.clone();
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                hooks.player_event(event);
+            }
+        });
+    }
+
+    pub fn session_lost(&self) {
+        let _ = self.queue.send(HookEvent::SessionLost);
+    }
+}
+
+type Env = Vec<(&'static str, String)>;
+
+async fn work(command: String, session: Session, mut events: mpsc::UnboundedReceiver<HookEvent>) {
+    // Play and pause events come often, keep the metadata of the last track around.
+    let mut described: Option<(SpotifyId, Env)> = None;
+    let mut current: Option<SpotifyId> = None;
+    while let Some(event) = events.recv().await {
+        let (name, track_id, mut env): (&str, Option<SpotifyId>, Env) = match event {
+            HookEvent::SessionLost => ("session_lost", None, vec![]),
+            HookEvent::Player(PlayerEvent::Started { track_id, .. })
+            | HookEvent::Player(PlayerEvent::Changed {
+                new_track_id: track_id,
+                ..
+            }) => {
+                if current == Some(track_id) {
+                    continue;
+                }
+                let mut env = vec![];
+                if let Some(old) = current.replace(track_id) {
+                    match old.to_base62() {
+                        Ok(id) => env.push(("OLD_TRACK_ID", id)),
+                        Err(e) => warn!("Could not encode track id {:?}: {}", old, e),
+                    }
+                }
+                ("change", Some(track_id), env)
+            }
+            HookEvent::Player(PlayerEvent::Playing {
+                track_id,
+                position_ms,
+                ..
+            }) => (
+                "play",
+                Some(track_id),
+                vec![("POSITION_MS", position_ms.to_string())],
+            ),
+            HookEvent::Player(PlayerEvent::Paused {
+                track_id,
+                position_ms,
+                ..
+            }) => (
+                "pause",
+                Some(track_id),
+                vec![("POSITION_MS", position_ms.to_string())],
+            ),
+            HookEvent::Player(PlayerEvent::Stopped { track_id, .. }) => {
+                current = None;
+                ("stop", Some(track_id), vec![])
+            }
+            HookEvent::Player(PlayerEvent::VolumeSet { volume }) => {
+                ("volume_set", None, vec![("VOLUME", volume.to_string())])
+            }
+            HookEvent::Player(other) => {
+                debug!("No hook for {:?}", other);
+                continue;
+            }
+        };
+        env.push(("PLAYER_EVENT", name.to_string()));
+        if let Some(id) = track_id {
+            match (id.to_base62(), id.to_uri()) {
+                (Ok(base62), Ok(uri)) => {
+                    env.push(("TRACK_ID", base62));
+                    env.push(("TRACK_URI", uri));
+                }
+                (Err(e), _) | (_, Err(e)) => warn!("Could not encode track id {:?}: {}", id, e),
+            }
+            match &described {
+                Some((described_id, details)) if *described_id == id => {
+                    env.extend(details.iter().cloned())
+                }
+                _ => {
+                    let details = describe(&session, id).await;
+                    env.extend(details.iter().cloned());
+                    described = Some((id, details));
+                }
+            }
+        }
+        run(&command, env);
+    }
+}
+
+/// Loads name, artists and duration of the track or episode.
+async fn describe(session: &Session, id: SpotifyId) -> Env {
+    let details = match id.audio_type {
+        SpotifyAudioType::Track => match Track::get(session, id).await {
+            Ok(track) => {
+                let mut artists = Vec::new();
+                for artist_id in track.artists {
+                    match Artist::get(session, artist_id).await {
+                        Ok(artist) => artists.push(artist.name),
+                        Err(e) => warn!("Could not load artist {:?}: {:?}", artist_id, e),
+                    }
+                }
+                Ok((track.name, artists.join(", "), track.duration))
+            }
+            Err(e) => Err(e),
+        },
+        SpotifyAudioType::Podcast => match Episode::get(session, id).await {
+            Ok(episode) => {
+                let show = Show::get(session, episode.show).await;
+                let show = show.map(|show| show.name).unwrap_or_default();
+                Ok((episode.name, show, episode.duration))
+            }
+            Err(e) => Err(e),
+        },
+        SpotifyAudioType::NonPlayable => return vec![],
+    };
+    match details {
+        Ok((name, artists, duration)) => vec![
+            ("TRACK_NAME", name),
+            ("ARTISTS", artists),
+            ("DURATION_MS", duration.to_string()),
+        ],
+        Err(e) => {
+            warn!("Could not load metadata of {:?}: {:?}", id, e);
+            vec![]
+        }
+    }
+}
+
+fn run(command: &str, env: Env) {
+    debug!("Running hook with {:?}", env);
+    let spawned = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env)
+        .spawn();
+    match spawned {
+        Ok(mut child) => {
+            tokio::spawn(async move {
+                match child.wait().await {
+                    Ok(status) if !status.success() => warn!("Hook failed with {}", status),
+                    Ok(_) => {}
+                    Err(e) => error!("Hook did not finish: {}", e),
+                }
+            });
+        }
+        Err(e) => error!("Could not run hook: {}", e),
+    }
+}
@@ -0,0 +1,261 @@
+//! Local control socket, so that other tools can drive a running russpot.
+//!
+//! The socket speaks JSON-RPC 2.0, one message per line. Methods:
+//! - `nav` with `{"command": ...}`: up, down, left, right, descend, back, forward,
+//!   playlists, search, shows, recent, top, devices or cycle_time_range.
+//! - `play` with an optional `{"uri": ..., "offset": ...}`: plays the uri,
+//!   or the item under cursor when there is none.
+//! - `pause`, `resume` and `next`.
+//! - `cursor` and `now_playing`: what is under the cursor, and what is playing.
+//! - `subscribe`: from then on, the connection also receives `cursor_moved`,
+//!   `track_changed` and `status_changed` notifications.
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use log::debug;
+use rspotify::model::Offset;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::broadcast;
+
+use crate::components::switchview;
+use crate::nowplaying::{NowPlaying, Playback};
+use crate::spotconn::model::{PlayTarget, SpotItem};
+use crate::spotconn::SpotConn;
+use crate::AppInput;
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// State shared between the GUI and the socket connections.
+struct Shared {
+    cursor: Mutex<Option<SpotItem>>,
+    /// Notifications for subscribers, already in JSON-RPC form.
+    events: broadcast::Sender<Value>,
+}
+
+fn shared() -> &'static Shared {
+    static SHARED: OnceLock<Shared> = OnceLock::new();
+    SHARED.get_or_init(|| Shared {
+        cursor: Mutex::new(None),
+        events: broadcast::channel(64).0,
+    })
+}
+
+/// Path of the socket: in the runtime dir, usually /run/user/<uid>/russpot.sock.
+/// Without one, in the temp dir, readable and writable only by the user.
+pub fn socket_path() -> PathBuf {
+    match dirs::runtime_dir() {
+        Some(dir) => dir.join("russpot.sock"),
+        None => {
+            let user = std::env::var("USER").unwrap_or_default();
+            std::env::temp_dir().join(format!("russpot-{}.sock", user))
+        }
+    }
+}
+
+/// Remembers the item under cursor, and tells the subscribers about it.
+pub fn cursor_moved(item: SpotItem) {
+    let _ = shared()
+        .events
+        .send(notification("cursor_moved", item.summary()));
+    *shared().cursor.lock().unwrap() = Some(item);
+}
+
+/// Accepts connections until the socket fails. Commands for the GUI go to app.
+pub async fn serve(app: relm4::Sender<AppInput>) -> std::io::Result<()> {
+    let path = socket_path();
+    if path.exists() {
+        // Another instance may still be listening there; leave its socket alone.
+        if UnixStream::connect(&path).await.is_ok() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                format!("another russpot is listening on {:?}", path),
+            ));
+        }
+        // A leftover from a previous run would make bind fail.
+        std::fs::remove_file(&path)?;
+    }
+    let listener = UnixListener::bind(&path)?;
+    // The runtime dir is private, but the temp dir fallback is not.
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    debug!("Listening for IPC on {:?}", path);
+    tokio::spawn(watch_playback());
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let app = app.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, app).await {
+                debug!("IPC connection closed: {}", e);
+            }
+        });
+    }
+}
+
+/// Turns playback changes into notifications.
+async fn watch_playback() {
+    let now_playing = NowPlaying::global();
+    let mut updates = now_playing.subscribe();
+    let mut previous = now_playing.current();
+    while updates.changed().await.is_ok() {
+        let fetched = updates.borrow_and_update().clone();
+        let events = &shared().events;
+        if previous.track != fetched.track {
+            let _ = events.send(notification("track_changed", playback_json(&fetched)));
+        } else if previous.status() != fetched.status() {
+            let _ = events.send(notification("status_changed", playback_json(&fetched)));
+        }
+        previous = fetched;
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    app: relm4::Sender<AppInput>,
+) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut events: Option<broadcast::Receiver<Value>> = None;
+    loop {
+        let reply = tokio::select! {
+            line = lines.next_line() => match line? {
+                Some(line) => handle_request(&line, &app, &mut events).await,
+                None => return Ok(()),
+            },
+            event = next_event(&mut events) => Some(event),
+        };
+        if let Some(reply) = reply {
+            writer.write_all(format!("{}\n", reply).as_bytes()).await?;
+        }
+    }
+}
+
+/// Waits for the next notification, or forever when not subscribed.
+async fn next_event(events: &mut Option<broadcast::Receiver<Value>>) -> Value {
+    loop {
+        match events {
+            Some(rx) => match rx.recv().await {
+                Ok(event) => return event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    debug!("IPC subscriber skipped {} events", n)
+                }
+                Err(broadcast::error::RecvError::Closed) => *events = None,
+            },
+            None => std::future::pending::<()>().await,
+        }
+    }
+}
+
+/// Returns the response, or None for notifications (requests without an id).
+async fn handle_request(
+    line: &str,
+    app: &relm4::Sender<AppInput>,
+    events: &mut Option<broadcast::Receiver<Value>>,
+) -> Option<Value> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+    };
+    let id = request.get("id").cloned();
+    let method = request["method"].as_str().unwrap_or_default();
+    let params = &request["params"];
+    let result = match method {
+        "nav" => nav_command(params["command"].as_str().unwrap_or_default())
+            .map(|cmd| {
+                app.emit(AppInput::Nav(cmd));
+                Value::Bool(true)
+            })
+            .ok_or((INVALID_PARAMS, "unknown nav command".to_string())),
+        "play" => play(params, app).await,
+        "pause" => done(SpotConn::global().pause().await),
+        "resume" => done(SpotConn::global().resume().await),
+        "next" => done(SpotConn::global().next().await),
+        "cursor" => Ok(cursor_json()),
+        "now_playing" => Ok(playback_json(&NowPlaying::global().current())),
+        "subscribe" => {
+            *events = Some(shared().events.subscribe());
+            Ok(Value::Bool(true))
+        }
+        _ => Err((METHOD_NOT_FOUND, format!("no method {:?}", method))),
+    };
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err((code, message)) => error_response(id, code, &message),
+    })
+}
+
+fn nav_command(command: &str) -> Option<switchview::In> {
+    use crate::navigation::NavCommand;
+    Some(match command {
+        "up" => switchview::In::Nav(NavCommand::Up),
+        "down" => switchview::In::Nav(NavCommand::Down),
+        "left" => switchview::In::Nav(NavCommand::Left),
+        "right" => switchview::In::Nav(NavCommand::Right),
+        "descend" => switchview::In::NavDescend,
+        "back" => switchview::In::NavBack,
+        "forward" => switchview::In::NavForward,
+        "playlists" => switchview::In::NavResetPlaylists,
+        "search" => switchview::In::NavResetSearch,
+        "shows" => switchview::In::NavResetShows,
+        "recent" => switchview::In::NavResetRecent,
+        "top" => switchview::In::NavResetTop,
+        "devices" => switchview::In::NavResetDevices,
+        "cycle_time_range" => switchview::In::CycleTimeRange,
+        _ => return None,
+    })
+}
+
+async fn play(params: &Value, app: &relm4::Sender<AppInput>) -> Result<Value, (i64, String)> {
+    let Some(uri) = params["uri"].as_str() else {
+        app.emit(AppInput::PlayNow);
+        return Ok(Value::Bool(true));
+    };
+    let offset = params["offset"]
+        .as_str()
+        .map(|o| Offset::Uri(o.to_string()));
+    let target = PlayTarget::from_uri(uri, offset)
+        .ok_or((INVALID_PARAMS, format!("{} is not playable", uri)))?;
+    done(SpotConn::global().play_context(target).await)
+}
+
+fn done(result: rspotify::ClientResult<()>) -> Result<Value, (i64, String)> {
+    NowPlaying::global().refresh();
+    result
+        .map(|_| Value::Bool(true))
+        .map_err(|e| (SERVER_ERROR, e.to_string()))
+}
+
+fn cursor_json() -> Value {
+    let cursor = shared().cursor.lock().unwrap();
+    cursor.as_ref().map_or(Value::Null, SpotItem::summary)
+}
+
+fn playback_json(playback: &Playback) -> Value {
+    json!({
+        "status": playback.status(),
+        "volume": playback.volume,
+        "position_ms": playback.position().as_millis() as u64,
+        "track": playback.track.as_ref().map(|track| json!({
+            "uri": track.uri,
+            "title": track.title,
+            "artists": track.artists,
+            "album": track.album,
+            "art_url": track.art_url,
+            "length_ms": track.length.as_millis() as u64,
+        })),
+    })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "method": method, "params": params})
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
@@ -0,0 +1,9 @@
+//! Parts of russpot shared between the GUI and the headless binaries.
+
+pub mod cache;
+pub mod hooks;
+pub mod mpris;
+pub mod nowplaying;
+pub mod offline;
+pub mod settings;
+pub mod spotconn;
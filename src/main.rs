@@ -1,33 +1,82 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
+use std::sync::Mutex;
+
 use gtk::prelude::*;
 use librespot::core::spotify_id::SpotifyId;
-use log::debug;
+use log::{debug, error};
 
 use relm4::{self, Component, ComponentController, Controller};
 use relm4::{gtk, ComponentParts, ComponentSender, RelmApp};
 
 use crate::actionbuilder::{AccelManager, ActionBuilder};
-use crate::components::actions::{Actions, ActionsOutput};
+use crate::components::actions::{Actions, ActionsOutput, ShareOp};
+use crate::components::denselist::SelectCommand;
+use crate::components::listorder::SortCommand;
+use crate::components::palette::{self, Command};
+use crate::components::playlistdialog::{self, PlaylistOp};
 use crate::components::switchview;
 use crate::navigation::NavCommand;
+use crate::offline::{Offline, QueuedWrite};
+use crate::settings::{PreferredDevice, Settings};
+use crate::spotconn::model::{LibraryChange, RadioSeed, SpotItem};
 use crate::spotconn::SpotConn;
 
 mod actionbuilder;
 mod components;
+mod ipc;
 pub(crate) mod navigation;
-mod spotconn;
+mod theme;
+
+const APP_ID: &str = "io.github.wojciechp.russpot";
+
+use russpot::{cache, mpris, nowplaying, offline, settings, spotconn};
 
 struct AppModel {
+    window: gtk::Window,
     actions: Controller<Actions>,
     switchview: Controller<switchview::Model>,
+    /// The currently open modal dialog, if any.
+    dialog: Option<Controller<playlistdialog::Model>>,
+    /// The command palette, while open.
+    palette: Option<Controller<palette::Model>>,
+    theme: theme::Theme,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 enum AppInput {
     PlayNow,
     SpircNow,
+    /// Open recommendations based on the item under cursor.
+    Radio,
+    /// Move playback to the device under cursor.
+    TransferPlayback,
+    /// Make the device under cursor the target for "Play now", or stop preferring it.
+    PreferDevice,
+    PlaylistDialog(PlaylistOp),
+    /// Open a Spotify URI or web link, see spotconn::model::parse_link.
+    Open(String),
+    /// Open the Spotify link in the clipboard.
+    OpenClipboard,
+    /// Copy the links to the selected items, or open the one under cursor.
+    Share(ShareOp),
+    /// Add the selected tracks and episodes to the playback queue.
+    Enqueue,
+    /// Add the selected tracks to Liked Songs.
+    Like,
+    /// Open the command palette.
+    Palette,
+    /// Run what was picked in the command palette.
+    Command(Command),
+    PaletteClosed,
+    /// The desktop now prefers dark colours, or light ones, or has no preference.
+    ColorScheme(Option<bool>),
+    LibraryChanged(LibraryChange),
+    /// Navigation requested from outside, e.g. over the IPC socket.
+    Nav(switchview::In),
+    CursorMoved(SpotItem),
+    DialogClosed,
 }
 
 #[relm4::component]
@@ -73,7 +122,9 @@ impl relm4::SimpleComponent for AppModel {
 
         let switchview: Controller<switchview::Model> = switchview::Model::builder()
             .launch(switchview::Init {})
-            .forward(sender.input_sender(), |msg| match msg {});
+            .forward(sender.input_sender(), |msg| match msg {
+                switchview::Out::CursorIsNowAt(item) => AppInput::CursorMoved(item),
+            });
 
         let actions_model = Actions::builder()
             .launch(())
@@ -86,20 +137,90 @@ impl relm4::SimpleComponent for AppModel {
                     println!("forwarding spirc");
                     AppInput::SpircNow
                 }
+                ActionsOutput::Radio => AppInput::Radio,
+                ActionsOutput::TransferPlayback => AppInput::TransferPlayback,
+                ActionsOutput::PreferDevice => AppInput::PreferDevice,
+                ActionsOutput::Playlist(op) => AppInput::PlaylistDialog(op),
+                ActionsOutput::Share(op) => AppInput::Share(op),
+                ActionsOutput::Enqueue => AppInput::Enqueue,
+                ActionsOutput::Like => AppInput::Like,
             });
         let model = AppModel {
+            window: window.clone(),
             switchview,
             actions: actions_model,
+            dialog: None,
+            palette: None,
+            theme: theme::Theme::install(&WidgetExt::display(&window)),
         };
         let actions_widget = model.actions.widget();
         let switchview_widget = model.switchview.widget();
 
         let widgets = view_output!();
 
+        relm4::spawn(async {
+            if let Err(e) = mpris::serve(SpotConn::global()).await {
+                error!("MPRIS service stopped: {}", e);
+            }
+        });
+        let ins = sender.input_sender().clone();
+        relm4::spawn(async move {
+            let result = theme::watch_portal(|dark| ins.emit(AppInput::ColorScheme(dark))).await;
+            if let Err(e) = result {
+                debug!("Not following the desktop colour scheme: {}", e);
+            }
+        });
+        let app = sender.input_sender().clone();
+        relm4::spawn(async move {
+            if let Err(e) = ipc::serve(app).await {
+                error!("IPC socket stopped: {}", e);
+            }
+        });
+        // Links queued by the open handler in main, which activates the app right after.
+        let ins = sender.input_sender().clone();
+        let win = window.clone();
+        relm4::main_application().connect_activate(move |_| {
+            for link in PENDING_LINKS.lock().unwrap().drain(..) {
+                ins.emit(AppInput::Open(link));
+            }
+            win.present();
+        });
+
         let ab = ActionBuilder::new(window.clone(), "global-controls");
         ab.add("quit", &["<primary>Q"], || {
             relm4::main_application().quit();
         });
+        let ins = sender.input_sender();
+        ab.add_emit(
+            "new-playlist",
+            &["<primary>N"],
+            ins,
+            AppInput::PlaylistDialog(PlaylistOp::Create),
+        );
+        ab.add_emit(
+            "new-playlist-from-list",
+            &["<primary><shift>N"],
+            ins,
+            AppInput::PlaylistDialog(PlaylistOp::CreateFromList),
+        );
+        ab.add_emit(
+            "rename-playlist",
+            &["F2"],
+            ins,
+            AppInput::PlaylistDialog(PlaylistOp::Rename),
+        );
+        ab.add_emit(
+            "describe-playlist",
+            &["<primary>E"],
+            ins,
+            AppInput::PlaylistDialog(PlaylistOp::Describe),
+        );
+        ab.add_emit(
+            "playlist-visibility",
+            &["<primary>P"],
+            ins,
+            AppInput::PlaylistDialog(PlaylistOp::Visibility),
+        );
 
         let mut am = AccelManager::new(&window, "global-navigation");
         let svs = &model.switchview.sender().clone();
@@ -109,6 +230,17 @@ impl relm4::SimpleComponent for AppModel {
         am.register_emit("right", &["L"], svs, switchview::In::Nav(NavCommand::Right));
         am.register_emit("descend", &["O"], svs, switchview::In::NavDescend); // O for Open
         am.register_emit("back", &["I"], svs, switchview::In::NavBack); // I because it's on the left side of O
+        am.register_emit("forward", &["U"], svs, switchview::In::NavForward); // U for Undo the back
+        am.register_emit("details", &["D"], svs, switchview::In::NavDetails);
+        ab.add_emit("history", &["<primary>H"], svs, switchview::In::ShowHistory);
+        for level in 0..9 {
+            ab.add_emit(
+                &format!("jump-to-level-{}", level + 1),
+                &[&format!("<alt>{}", level + 1)],
+                svs,
+                switchview::In::JumpToLevel(level),
+            );
+        }
 
         am.register_emit("reset-search", &["1"], svs, switchview::In::NavResetSearch);
         am.register_emit(
@@ -117,13 +249,130 @@ impl relm4::SimpleComponent for AppModel {
             svs,
             switchview::In::NavResetPlaylists,
         );
+        am.register_emit("reset-shows", &["3"], svs, switchview::In::NavResetShows);
+        am.register_emit("reset-recent", &["4"], svs, switchview::In::NavResetRecent);
+        am.register_emit("reset-top", &["5"], svs, switchview::In::NavResetTop);
+        am.register_emit("cycle-range", &["T"], svs, switchview::In::CycleTimeRange);
+        am.register_emit(
+            "sort",
+            &["S"],
+            svs,
+            switchview::In::Sort(SortCommand::NextField),
+        );
+        am.register_emit(
+            "sort-reverse",
+            &["<shift>S"],
+            svs,
+            switchview::In::Sort(SortCommand::Reverse),
+        );
+        am.register_emit(
+            "group",
+            &["G"],
+            svs,
+            switchview::In::Sort(SortCommand::NextGrouping),
+        );
 
+        am.register_emit(
+            "reset-devices",
+            &["6"],
+            svs,
+            switchview::In::NavResetDevices,
+        );
+        am.register_emit("radio", &["R"], sender.input_sender(), AppInput::Radio);
+        am.register_emit(
+            "transfer-playback",
+            &["<shift>T"],
+            sender.input_sender(),
+            AppInput::TransferPlayback,
+        );
+        am.register_emit(
+            "prefer-device",
+            &["<shift>D"],
+            sender.input_sender(),
+            AppInput::PreferDevice,
+        );
+        // Through AccelManager, so that Ctrl+Delete still deletes a word in the searchbox.
+        am.register_emit(
+            "delete-playlist",
+            &["<primary>Delete"],
+            sender.input_sender(),
+            AppInput::PlaylistDialog(PlaylistOp::Delete),
+        );
+        // Through AccelManager, so that pasting into the searchbox still works.
+        am.register_emit(
+            "open-clipboard",
+            &["<primary>V"],
+            sender.input_sender(),
+            AppInput::OpenClipboard,
+        );
+        am.register_emit(
+            "copy-uri",
+            &["Y"],
+            sender.input_sender(),
+            AppInput::Share(ShareOp::CopyUri),
+        );
+        am.register_emit(
+            "copy-link",
+            &["<shift>Y"],
+            sender.input_sender(),
+            AppInput::Share(ShareOp::CopyLink),
+        );
+        am.register_emit(
+            "copy-line",
+            &["<primary>Y"],
+            sender.input_sender(),
+            AppInput::Share(ShareOp::CopyLine),
+        );
+        am.register_emit(
+            "open-in-browser",
+            &["<shift>B"],
+            sender.input_sender(),
+            AppInput::Share(ShareOp::OpenInBrowser),
+        );
+        am.register_emit(
+            "visual-mode",
+            &["V"],
+            svs,
+            switchview::In::Select(SelectCommand::Visual),
+        );
+        am.register_emit(
+            "toggle-selected",
+            &["X"],
+            svs,
+            switchview::In::Select(SelectCommand::Toggle),
+        );
+        am.register_emit(
+            "clear-selection",
+            &["<shift>V"],
+            svs,
+            switchview::In::Select(SelectCommand::Clear),
+        );
+        am.register_emit("enqueue", &["E"], sender.input_sender(), AppInput::Enqueue);
+        am.register_emit("like", &["<shift>L"], sender.input_sender(), AppInput::Like);
+        am.register_emit(
+            "add-to-playlist",
+            &["<shift>A"],
+            sender.input_sender(),
+            AppInput::PlaylistDialog(PlaylistOp::AddItems),
+        );
+        am.register_emit(
+            "remove-from-playlist",
+            &["<shift>X"],
+            sender.input_sender(),
+            AppInput::PlaylistDialog(PlaylistOp::RemoveItems),
+        );
         am.register_emit(
             "play_now",
             &["<shift>P"],
             sender.input_sender(),
             AppInput::PlayNow,
         );
+        am.register_emit(
+            "command-palette",
+            &["colon"],
+            sender.input_sender(),
+            AppInput::Palette,
+        );
         am.connect();
 
         ComponentParts { model, widgets }
@@ -132,10 +381,12 @@ impl relm4::SimpleComponent for AppModel {
     fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
         match message {
             AppInput::PlayNow => {
-                if let Some((ctx, offset)) = self.switchview.model().play_context() {
+                if let Some(target) = self.switchview.model().play_context() {
                     debug!("play now -> ctx is some");
                     _sender.oneshot_command(async move {
-                        SpotConn::global().play_context(ctx, offset).await;
+                        if let Err(e) = SpotConn::global().play_context(target).await {
+                            error!("Could not start playback: {:?}", e);
+                        }
                     })
                 } else {
                     debug!("playnow -> no ctx");
@@ -144,13 +395,299 @@ impl relm4::SimpleComponent for AppModel {
             AppInput::SpircNow => _sender.oneshot_command(async move {
                 SpotConn::global().play_on_spirc().await;
             }),
+            AppInput::Radio => {
+                let item = self.switchview.model().current_item();
+                match item.as_ref().and_then(RadioSeed::from_item) {
+                    Some(seed) => self
+                        .switchview
+                        .emit(switchview::In::Push(SpotItem::Radio(seed))),
+                    None => debug!("cannot start radio from {:?}", item),
+                }
+            }
+            AppInput::TransferPlayback => {
+                let item = self.switchview.model().current_item();
+                match item {
+                    Some(SpotItem::Device(dev)) if dev.id.is_some() => {
+                        _sender.oneshot_command(async move {
+                            let id = dev.id.unwrap();
+                            if let Err(e) = SpotConn::global().transfer_playback(&id).await {
+                                error!("Could not transfer playback to {}: {:?}", dev.name, e);
+                            }
+                        })
+                    }
+                    other => debug!("cannot transfer playback to {:?}", other),
+                }
+            }
+            AppInput::PreferDevice => {
+                let item = self.switchview.model().current_item();
+                match item {
+                    Some(SpotItem::Device(dev)) if dev.id.is_some() => {
+                        let id = dev.id.unwrap();
+                        Settings::update(|settings| {
+                            let already = settings
+                                .preferred_device
+                                .as_ref()
+                                .is_some_and(|p| p.id == id);
+                            settings.preferred_device = if already {
+                                None
+                            } else {
+                                Some(PreferredDevice { id, name: dev.name })
+                            };
+                        });
+                        debug!("preferred device: {:?}", Settings::get().preferred_device);
+                        self.switchview.emit(switchview::In::RefreshDevices);
+                    }
+                    other => debug!("cannot play on {:?}", other),
+                }
+            }
+            AppInput::PlaylistDialog(op) => {
+                if self.dialog.is_some() {
+                    debug!("a dialog is already open, ignoring {:?}", op);
+                    return;
+                }
+                let Some(kind) = self.playlist_dialog_kind(op) else {
+                    debug!("{:?}: nothing to apply it to", op);
+                    return;
+                };
+                let dialog = playlistdialog::Model::builder()
+                    .transient_for(&self.window)
+                    .launch(kind)
+                    .forward(_sender.input_sender(), |msg| match msg {
+                        playlistdialog::Out::Changed(change) => AppInput::LibraryChanged(change),
+                        playlistdialog::Out::Closed => AppInput::DialogClosed,
+                    });
+                self.dialog = Some(dialog);
+            }
+            AppInput::Open(link) => {
+                let Some(uri) = spotconn::model::parse_link(&link) else {
+                    debug!("{:?} is not a Spotify link", link);
+                    return;
+                };
+                let switchview = self.switchview.sender().clone();
+                _sender.oneshot_command(async move {
+                    let item = match SpotConn::global().lookup(&uri).await {
+                        Ok(Some(item)) => item,
+                        Ok(None) => return debug!("cannot open {}", uri),
+                        Err(e) => return error!("Could not open {}: {:?}", uri, e),
+                    };
+                    // Single tracks and episodes open in their album or show.
+                    switchview.emit(match item {
+                        SpotItem::Track(ft) => {
+                            switchview::In::PushAt(SpotItem::Album(ft.album), uri)
+                        }
+                        SpotItem::Episode(ep) => {
+                            switchview::In::PushAt(SpotItem::Show(ep.show), uri)
+                        }
+                        collection => switchview::In::Push(collection),
+                    });
+                });
+            }
+            AppInput::OpenClipboard => {
+                let ins = _sender.input_sender().clone();
+                self.window
+                    .clipboard()
+                    .read_text_async(gtk::gio::Cancellable::NONE, move |text| match text {
+                        Ok(Some(text)) => ins.emit(AppInput::Open(text.to_string())),
+                        Ok(None) => debug!("no text in the clipboard"),
+                        Err(e) => error!("Cannot read the clipboard: {}", e),
+                    });
+            }
+            AppInput::Share(op) => {
+                // Only the item under cursor opens in the browser, the links to all are copied.
+                let items = match op {
+                    ShareOp::OpenInBrowser => {
+                        self.switchview.model().current_item().into_iter().collect()
+                    }
+                    _ => self.switchview.model().selection(),
+                };
+                let lines: Vec<String> = items
+                    .iter()
+                    .filter_map(|item| match op {
+                        ShareOp::CopyUri => item.uri(),
+                        ShareOp::CopyLink | ShareOp::OpenInBrowser => item.web_url(),
+                        ShareOp::CopyLine => Some(item.share_line()),
+                    })
+                    .collect();
+                if lines.is_empty() {
+                    debug!("{:?}: no links in {:?}", op, items);
+                    return;
+                }
+                let text = lines.join("\n");
+                if let ShareOp::OpenInBrowser = op {
+                    gtk::UriLauncher::new(&text).launch(
+                        Some(&self.window),
+                        gtk::gio::Cancellable::NONE,
+                        move |launched| {
+                            if let Err(e) = launched {
+                                error!("Could not open {}: {}", text, e);
+                            }
+                        },
+                    );
+                } else {
+                    self.window.clipboard().set_text(&text);
+                }
+            }
+            AppInput::Enqueue => {
+                let ids: Vec<_> = self
+                    .switchview
+                    .model()
+                    .selection()
+                    .iter()
+                    .filter(|item| item.is_playable())
+                    .filter_map(SpotItem::playable_id)
+                    .collect();
+                if ids.is_empty() {
+                    debug!("nothing to queue");
+                    return;
+                }
+                _sender.oneshot_command(async move {
+                    let result = SpotConn::global().add_to_queue(ids).await;
+                    Offline::global().observe(&result);
+                    if let Err(e) = result {
+                        error!("Could not add to the queue: {:?}", e);
+                    }
+                })
+            }
+            AppInput::Like => {
+                let items: Vec<SpotItem> = self
+                    .switchview
+                    .model()
+                    .selection()
+                    .into_iter()
+                    .filter(|item| matches!(item, SpotItem::Track(_)))
+                    .collect();
+                if items.is_empty() {
+                    debug!("no tracks to like");
+                    return;
+                }
+                _sender.oneshot_command(async move {
+                    let write = QueuedWrite::SaveTracks { items };
+                    if let Err(e) = Offline::global().perform_or_queue(write).await {
+                        error!("Could not like the tracks: {:?}", e);
+                    }
+                })
+            }
+            AppInput::Palette => {
+                if self.palette.is_some() || self.dialog.is_some() {
+                    return;
+                }
+                let palette = palette::Model::builder()
+                    .transient_for(&self.window)
+                    .launch(())
+                    .forward(_sender.input_sender(), |msg| match msg {
+                        palette::Out::Run(command) => AppInput::Command(command),
+                        palette::Out::Closed => AppInput::PaletteClosed,
+                    });
+                self.palette = Some(palette);
+            }
+            AppInput::Command(command) => self.run_command(command, &_sender),
+            AppInput::PaletteClosed => self.palette = None,
+            AppInput::ColorScheme(dark) => self.theme.set_portal_dark(dark),
+            AppInput::LibraryChanged(change) => {
+                self.switchview.emit(switchview::In::Library(change));
+            }
+            AppInput::DialogClosed => self.dialog = None,
+            AppInput::Nav(msg) => self.switchview.emit(msg),
+            AppInput::CursorMoved(item) => ipc::cursor_moved(item),
+        }
+    }
+}
+
+impl AppModel {
+    /// Figures out what the playlist operation applies to. Edits apply to the playlist
+    /// under cursor, or if there is none, to the playlist currently displayed.
+    fn playlist_dialog_kind(&self, op: PlaylistOp) -> Option<playlistdialog::Kind> {
+        use playlistdialog::Kind;
+        let switchview = self.switchview.model();
+        let playlist = match (switchview.current_item(), switchview.current_source()) {
+            (Some(SpotItem::Playlist(sp)), _) | (_, Some(SpotItem::Playlist(sp))) => Some(sp),
+            _ => None,
+        };
+        match op {
+            PlaylistOp::Create => Some(Kind::Create {
+                name: String::new(),
+                seed: vec![],
+            }),
+            PlaylistOp::CreateFromList => Some(Kind::Create {
+                name: switchview
+                    .current_source()
+                    .map(|source| source.name())
+                    .unwrap_or_default(),
+                seed: switchview.current_items(),
+            }),
+            PlaylistOp::Rename => playlist.map(Kind::Rename),
+            PlaylistOp::Describe => playlist.map(Kind::Describe),
+            PlaylistOp::Visibility => playlist.map(Kind::Visibility),
+            PlaylistOp::Delete => playlist.map(Kind::Delete),
+            PlaylistOp::AddItems => {
+                let items = playable(switchview.selection());
+                (!items.is_empty()).then_some(Kind::AddItems { items, to: None })
+            }
+            PlaylistOp::RemoveItems => {
+                let items = playable(switchview.selection());
+                match switchview.current_source() {
+                    Some(SpotItem::Playlist(from)) if !items.is_empty() => {
+                        Some(Kind::RemoveItems { from, items })
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn run_command(&self, command: Command, sender: &ComponentSender<Self>) {
+        match command {
+            Command::Action(name) => {
+                if let Err(e) = self.window.activate_action(&name, None) {
+                    error!("Could not run {}: {}", name, e);
+                }
+            }
+            Command::Search(st, query) => self.switchview.emit(switchview::In::Search(st, query)),
+            Command::Open(link) => sender.input(AppInput::Open(link)),
+            Command::Volume(percent) => sender.oneshot_command(async move {
+                if let Err(e) = SpotConn::global().set_volume(percent).await {
+                    error!("Could not set the volume to {}: {:?}", percent, e);
+                }
+            }),
+            Command::Device(dev) => {
+                let Some(id) = dev.id else {
+                    return debug!("cannot transfer playback to {}: no id", dev.name);
+                };
+                sender.oneshot_command(async move {
+                    if let Err(e) = SpotConn::global().transfer_playback(&id).await {
+                        error!("Could not transfer playback to {}: {:?}", dev.name, e);
+                    }
+                })
+            }
+            Command::Playlist(sp) => self
+                .switchview
+                .emit(switchview::In::Push(SpotItem::Playlist(sp))),
         }
     }
 }
 
+/// Only the tracks and episodes, which playlists can hold.
+fn playable(items: Vec<SpotItem>) -> Vec<SpotItem> {
+    items
+        .into_iter()
+        .filter(|item| item.playable_id().is_some())
+        .collect()
+}
+
+/// Links passed on the command line, waiting for the window to open them.
+static PENDING_LINKS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
 fn main() {
     env_logger::init();
-    let app = RelmApp::new("relm4.test.simple_manual");
-    app.set_global_css(include_str!("style.css"));
+    let app = RelmApp::new(APP_ID).with_args(std::env::args().collect());
+    let gapp = relm4::main_application();
+    gapp.set_flags(gtk::gio::ApplicationFlags::HANDLES_OPEN);
+    // With links, GApplication emits open instead of activate, also when russpot is
+    // already running: the first instance gets them. Either way, activate takes it from here.
+    gapp.connect_open(|gapp, files, _| {
+        let links = files.iter().map(|file| file.uri().to_string());
+        PENDING_LINKS.lock().unwrap().extend(links);
+        gapp.activate();
+    });
     app.run::<AppModel>(());
 }
@@ -0,0 +1,320 @@
+//! MPRIS D-Bus service, so that media keys and desktop widgets can see and control playback.
+//! See https://specifications.freedesktop.org/mpris-spec/latest/ for the interfaces.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use log::{debug, error};
+use zbus::object_server::SignalContext;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+use zbus::{connection, interface};
+
+use crate::nowplaying::{NowPlaying, Playback, Track};
+use crate::spotconn::model::PlayTarget;
+use crate::spotconn::SpotConn;
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.russpot";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+/// Position jumps bigger than this, beyond the time that passed, are reported as seeks.
+const SEEK_THRESHOLD: Duration = Duration::from_secs(3);
+
+/// Registers the MPRIS service on the session bus, and keeps it up to date until the bus fails.
+pub async fn serve(spot: &'static SpotConn) -> zbus::Result<()> {
+    serve_on(connection::Builder::session()?, spot, NowPlaying::global()).await
+}
+
+/// Like serve, on the given bus, reporting the given playback state.
+pub async fn serve_on(
+    bus: connection::Builder<'_>,
+    spot: &'static SpotConn,
+    now_playing: &'static NowPlaying,
+) -> zbus::Result<()> {
+    let player = Player { spot, now_playing };
+    let conn = bus
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, Root)?
+        .serve_at(OBJECT_PATH, player)?
+        .build()
+        .await?;
+    debug!("MPRIS service registered as {}", BUS_NAME);
+
+    let iface_ref = conn
+        .object_server()
+        .interface::<_, Player>(OBJECT_PATH)
+        .await?;
+    let mut updates = now_playing.subscribe();
+    let mut previous = now_playing.current();
+    while updates.changed().await.is_ok() {
+        let fetched = updates.borrow_and_update().clone();
+        let iface = iface_ref.get().await;
+        let ctxt = iface_ref.signal_context();
+        if previous.status() != fetched.status() {
+            iface.playback_status_changed(ctxt).await?;
+        }
+        if previous.track != fetched.track {
+            iface.metadata_changed(ctxt).await?;
+        } else if previous.position().abs_diff(fetched.position()) > SEEK_THRESHOLD {
+            Player::seeked(ctxt, micros(fetched.position())).await?;
+        }
+        if previous.volume != fetched.volume {
+            iface.volume_changed(ctxt).await?;
+        }
+        previous = fetched;
+    }
+    Ok(())
+}
+
+/// MPRIS wants a D-Bus object path as the track id, derived here from the Spotify URI.
+fn object_path(track: &Track) -> String {
+    format!(
+        "/io/github/wojciechp/russpot/{}",
+        track.uri.trim_start_matches("spotify:").replace(':', "/")
+    )
+}
+
+fn micros(d: Duration) -> i64 {
+    d.as_micros().try_into().unwrap_or(i64::MAX)
+}
+
+/// The org.mpris.MediaPlayer2 interface. Russpot cannot be raised or quit over D-Bus.
+struct Root;
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "Russpot"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        vec!["spotify".to_string()]
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// The org.mpris.MediaPlayer2.Player interface, mapped onto SpotConn playback calls.
+struct Player {
+    spot: &'static SpotConn,
+    now_playing: &'static NowPlaying,
+}
+
+impl Player {
+    fn playback(&self) -> Playback {
+        self.now_playing.current()
+    }
+
+    /// Logs failures, and picks up the effects of the command.
+    fn done(&self, what: &str, result: rspotify::ClientResult<()>) {
+        if let Err(e) = result {
+            error!("MPRIS {} failed: {:?}", what, e);
+        }
+        self.now_playing.refresh();
+    }
+
+    async fn seek_to(&self, position: Duration) {
+        let position = chrono::Duration::from_std(position).unwrap_or_default();
+        self.done("seek", self.spot.seek(position).await);
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    async fn play(&self) {
+        self.done("play", self.spot.resume().await);
+    }
+
+    async fn pause(&self) {
+        self.done("pause", self.spot.pause().await);
+    }
+
+    async fn play_pause(&self) {
+        if self.playback().playing {
+            self.pause().await
+        } else {
+            self.play().await
+        }
+    }
+
+    /// Spotify has no real stop, pausing is the closest.
+    async fn stop(&self) {
+        self.pause().await
+    }
+
+    async fn next(&self) {
+        self.done("next", self.spot.next().await);
+    }
+
+    async fn previous(&self) {
+        self.done("previous", self.spot.previous().await);
+    }
+
+    /// Moves by offset microseconds. Seeking past the end skips to the next track.
+    async fn seek(&self, offset: i64) {
+        let playback = self.playback();
+        let Some(track) = &playback.track else {
+            return;
+        };
+        let delta = Duration::from_micros(offset.unsigned_abs());
+        let position = if offset < 0 {
+            playback.position().saturating_sub(delta)
+        } else {
+            playback.position() + delta
+        };
+        if position >= track.length {
+            self.next().await
+        } else {
+            self.seek_to(position).await
+        }
+    }
+
+    async fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        let playback = self.playback();
+        let Some(track) = &playback.track else {
+            return;
+        };
+        // Per the spec, requests for another track, or out of range, are ignored.
+        let position = Duration::from_micros(position.try_into().unwrap_or(u64::MAX));
+        if track_id.as_str() != object_path(track) || position > track.length {
+            debug!("Ignoring SetPosition {} for {}", track_id, track.uri);
+            return;
+        }
+        self.seek_to(position).await
+    }
+
+    async fn open_uri(&self, uri: String) {
+        match PlayTarget::from_uri(&uri, None) {
+            Some(target) => {
+                if let Err(e) = self.spot.play_context(target).await {
+                    error!("MPRIS cannot play {}: {:?}", uri, e);
+                }
+                self.now_playing.refresh();
+            }
+            None => error!("MPRIS cannot open {}", uri),
+        }
+    }
+
+    #[zbus(signal)]
+    async fn seeked(ctxt: &SignalContext<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        self.playback().status()
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let mut metadata = HashMap::new();
+        let mut insert = |key: &str, value: Value<'_>| match value.try_to_owned() {
+            Ok(owned) => {
+                metadata.insert(key.to_string(), owned);
+            }
+            Err(e) => error!("Cannot convert MPRIS {}: {}", key, e),
+        };
+        match self.playback().track {
+            Some(track) => {
+                let path = object_path(&track);
+                let path = ObjectPath::try_from(path.as_str())
+                    .unwrap_or_else(|_| ObjectPath::from_static_str_unchecked(NO_TRACK));
+                insert("mpris:trackid", path.into());
+                insert("mpris:length", micros(track.length).into());
+                insert("xesam:title", track.title.into());
+                insert("xesam:artist", track.artists.into());
+                insert("xesam:album", track.album.into());
+                if let Some(url) = track.art_url {
+                    insert("mpris:artUrl", url.into());
+                }
+            }
+            None => insert(
+                "mpris:trackid",
+                ObjectPath::from_static_str_unchecked(NO_TRACK).into(),
+            ),
+        }
+        metadata
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.playback().volume.unwrap_or(0) as f64 / 100.0
+    }
+
+    #[zbus(property)]
+    async fn set_volume(&mut self, volume: f64) {
+        let percent = (volume.clamp(0.0, 1.0) * 100.0).round() as u8;
+        self.done("volume", self.spot.set_volume(percent).await);
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        micros(self.playback().position())
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,209 @@
+//! Now playing: the playback state of the user, shared by everything that reports it.
+//!
+//! The Web API does not push playback changes, so the state is polled,
+//! and re-fetched right away when someone asks for a refresh, e.g. after a command.
+//! The polls also tell offline mode whether Spotify is reachable.
+
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use log::{debug, error};
+use rspotify::model::{CurrentPlaybackContext, PlayableItem};
+use rspotify::prelude::*;
+use tokio::sync::{watch, Notify};
+
+use crate::offline::{is_offline_error, Offline};
+use crate::spotconn::SpotConn;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// While offline, polls only check whether Spotify is back, no need to hurry.
+const OFFLINE_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+pub struct NowPlaying {
+    state: watch::Sender<Playback>,
+    refresh: Notify,
+}
+
+impl NowPlaying {
+    /// Returns the global poller, starting it on first use.
+    /// Must be called from within the tokio runtime.
+    pub fn global() -> &'static NowPlaying {
+        static NOW_PLAYING: OnceLock<NowPlaying> = OnceLock::new();
+        let mut started = false;
+        let now_playing = NOW_PLAYING.get_or_init(|| {
+            started = true;
+            NowPlaying::new()
+        });
+        if started {
+            tokio::spawn(now_playing.poll(SpotConn::global()));
+        }
+        now_playing
+    }
+
+    /// A state nobody polls, which only changes with publish.
+    pub fn new() -> NowPlaying {
+        NowPlaying {
+            state: watch::Sender::new(Playback::default()),
+            refresh: Notify::new(),
+        }
+    }
+
+    /// Replaces the state, and tells the subscribers.
+    pub fn publish(&self, playback: Playback) {
+        self.state.send_replace(playback);
+    }
+
+    /// Returns the last fetched state.
+    pub fn current(&self) -> Playback {
+        self.state.borrow().clone()
+    }
+
+    /// Receives every fetched state, whether anything changed or not.
+    pub fn subscribe(&self) -> watch::Receiver<Playback> {
+        self.state.subscribe()
+    }
+
+    /// Fetches the state now, instead of waiting for the next poll.
+    pub fn refresh(&self) {
+        self.refresh.notify_one();
+    }
+
+    async fn poll(&self, spot: &SpotConn) {
+        let offline = Offline::global();
+        loop {
+            let result = spot.playback().await;
+            offline.observe(&result);
+            match result {
+                Ok(ctx) => self.publish(Playback::from_context(ctx)),
+                Err(e) if is_offline_error(&e) => debug!("Offline, no playback state: {}", e),
+                Err(e) => error!("Could not fetch playback state: {:?}", e),
+            }
+            let interval = if offline.is_online() {
+                POLL_INTERVAL
+            } else {
+                OFFLINE_POLL_INTERVAL
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {},
+                _ = self.refresh.notified() => {},
+            }
+        }
+    }
+}
+
+impl Default for NowPlaying {
+    fn default() -> Self {
+        NowPlaying::new()
+    }
+}
+
+/// Playback state as last seen on the Web API.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    pub playing: bool,
+    pub track: Option<Track>,
+    pub volume: Option<u32>,
+    progress: Duration,
+    fetched_at: Instant,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            playing: false,
+            track: None,
+            volume: None,
+            progress: Duration::ZERO,
+            fetched_at: Instant::now(),
+        }
+    }
+}
+
+impl Playback {
+    /// The state as of now, at the given position in the track.
+    pub fn new(
+        playing: bool,
+        track: Option<Track>,
+        volume: Option<u32>,
+        progress: Duration,
+    ) -> Playback {
+        Playback {
+            playing,
+            track,
+            volume,
+            progress,
+            fetched_at: Instant::now(),
+        }
+    }
+
+    fn from_context(ctx: Option<CurrentPlaybackContext>) -> Playback {
+        let Some(ctx) = ctx else {
+            return Playback::default();
+        };
+        Playback::new(
+            ctx.is_playing,
+            ctx.item.as_ref().and_then(Track::from_item),
+            ctx.device.volume_percent,
+            ctx.progress
+                .and_then(|p| p.to_std().ok())
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Current position, extrapolated from the last fetch.
+    pub fn position(&self) -> Duration {
+        let position = if self.playing {
+            self.progress + self.fetched_at.elapsed()
+        } else {
+            self.progress
+        };
+        match &self.track {
+            Some(track) => position.min(track.length),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Status in MPRIS terms: "Playing", "Paused" or "Stopped".
+    pub fn status(&self) -> &'static str {
+        match (&self.track, self.playing) {
+            (None, _) => "Stopped",
+            (Some(_), true) => "Playing",
+            (Some(_), false) => "Paused",
+        }
+    }
+}
+
+/// The bits of a track or episode which players show.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub uri: String,
+    pub title: String,
+    pub artists: Vec<String>,
+    /// Album of the track, or show of the episode.
+    pub album: String,
+    pub art_url: Option<String>,
+    pub length: Duration,
+}
+
+impl Track {
+    fn from_item(item: &PlayableItem) -> Option<Track> {
+        match item {
+            PlayableItem::Track(ft) => Some(Track {
+                uri: ft.id.as_ref()?.uri(),
+                title: ft.name.clone(),
+                artists: ft.artists.iter().map(|a| a.name.clone()).collect(),
+                album: ft.album.name.clone(),
+                art_url: ft.album.images.first().map(|img| img.url.clone()),
+                length: ft.duration.to_std().unwrap_or_default(),
+            }),
+            PlayableItem::Episode(ep) => Some(Track {
+                uri: ep.id.uri(),
+                title: ep.name.clone(),
+                artists: vec![ep.show.publisher.clone()],
+                album: ep.show.name.clone(),
+                art_url: ep.images.first().map(|img| img.url.clone()),
+                length: ep.duration.to_std().unwrap_or_default(),
+            }),
+        }
+    }
+}
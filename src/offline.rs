@@ -0,0 +1,281 @@
+//! Offline mode: what russpot does while Spotify cannot be reached.
+//!
+//! Lists are then served from the metadata cache only, and library writes are queued
+//! in offline-queue.json in the cache directory, to be replayed in order once any request
+//! succeeds again. Whoever talks to the Web API reports the outcome via `observe`.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use log::{debug, error, info, warn};
+use rspotify::http::HttpError;
+use rspotify::model::{PlayableId, PlaylistId};
+use rspotify::{ClientError, ClientResult};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::cache::cache_path;
+use crate::spotconn::model::{PlaylistDetails, PlaylistEdit, SpotItem};
+use crate::spotconn::SpotConn;
+
+/// A library write made while offline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum QueuedWrite {
+    CreatePlaylist {
+        details: PlaylistDetails,
+        seed: Vec<SpotItem>,
+    },
+    EditPlaylist {
+        uri: String,
+        edit: PlaylistEdit,
+    },
+    DeletePlaylist {
+        uri: String,
+    },
+    AddToPlaylist {
+        uri: String,
+        items: Vec<SpotItem>,
+    },
+    RemoveFromPlaylist {
+        uri: String,
+        items: Vec<SpotItem>,
+    },
+    SaveTracks {
+        items: Vec<SpotItem>,
+    },
+}
+
+pub struct Offline {
+    online: watch::Sender<bool>,
+    queue: Mutex<Vec<QueuedWrite>>,
+    replaying: AtomicBool,
+}
+
+impl Offline {
+    /// Returns the global state. Until a request fails, russpot assumes it is online.
+    pub fn global() -> &'static Offline {
+        static OFFLINE: OnceLock<Offline> = OnceLock::new();
+        OFFLINE.get_or_init(|| Offline {
+            online: watch::Sender::new(true),
+            queue: Mutex::new(load_queue()),
+            replaying: AtomicBool::new(false),
+        })
+    }
+
+    pub fn is_online(&self) -> bool {
+        *self.online.borrow()
+    }
+
+    /// Receives every switch between online and offline.
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.online.subscribe()
+    }
+
+    /// Records the outcome of a request. A failure to reach Spotify switches to offline mode,
+    /// anything else, even a client error response such as 404, means Spotify is reachable.
+    /// Must be called from within the tokio runtime.
+    pub fn observe<T>(&'static self, result: &ClientResult<T>) {
+        let online = !matches!(result, Err(e) if is_offline_error(e));
+        let changed = self.online.send_if_modified(|current| {
+            let changed = *current != online;
+            *current = online;
+            changed
+        });
+        if changed && online {
+            info!("Back online");
+        } else if changed {
+            warn!("Spotify is unreachable, going offline");
+        }
+        if online && !self.queue.lock().unwrap().is_empty() {
+            tokio::spawn(self.replay());
+        }
+    }
+
+    /// Keeps the write until Spotify is reachable again.
+    pub fn enqueue(&self, write: QueuedWrite) {
+        debug!("Queueing {:?} until back online", write);
+        let mut queue = self.queue.lock().unwrap();
+        queue.push(write);
+        save_queue(&queue);
+    }
+
+    /// Performs the write right away, or queues it if Spotify cannot be reached.
+    pub async fn perform_or_queue(&'static self, write: QueuedWrite) -> ClientResult<()> {
+        if self.is_online() {
+            let result = perform(&write).await;
+            self.observe(&result);
+            match result {
+                Err(e) if is_offline_error(&e) => {}
+                result => return result,
+            }
+        }
+        self.enqueue(write);
+        Ok(())
+    }
+
+    /// Sends the queued writes, oldest first, until the queue is empty or Spotify is gone again.
+    async fn replay(&self) {
+        if self.replaying.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        loop {
+            let Some(write) = self.queue.lock().unwrap().first().cloned() else {
+                break;
+            };
+            let result = perform(&write).await;
+            if matches!(&result, Err(e) if is_offline_error(e)) {
+                break;
+            }
+            if let Err(e) = result {
+                // Retrying would not help, e.g. the playlist is gone by now.
+                error!("Dropping queued {:?}: {:?}", write, e);
+            }
+            let mut queue = self.queue.lock().unwrap();
+            queue.remove(0);
+            save_queue(&queue);
+        }
+        self.replaying.store(false, Ordering::SeqCst);
+    }
+}
+
+async fn perform(write: &QueuedWrite) -> ClientResult<()> {
+    let spot = SpotConn::global();
+    match write {
+        QueuedWrite::CreatePlaylist { details, seed } => {
+            let seed: Vec<PlayableId<'static>> =
+                seed.iter().filter_map(SpotItem::playable_id).collect();
+            spot.create_playlist(details, seed).await.map(|_| ())
+        }
+        QueuedWrite::EditPlaylist { uri, edit } => {
+            spot.change_playlist_details(playlist_id(uri)?, edit).await
+        }
+        QueuedWrite::DeletePlaylist { uri } => spot.delete_playlist(playlist_id(uri)?).await,
+        QueuedWrite::AddToPlaylist { uri, items } => {
+            let items = items.iter().filter_map(SpotItem::playable_id).collect();
+            spot.add_to_playlist(playlist_id(uri)?, items)
+                .await
+                .map(|_| ())
+        }
+        QueuedWrite::RemoveFromPlaylist { uri, items } => {
+            let items = items
+                .iter()
+                .filter_map(SpotItem::original_playable_id)
+                .collect();
+            spot.remove_from_playlist(playlist_id(uri)?, items)
+                .await
+                .map(|_| ())
+        }
+        QueuedWrite::SaveTracks { items } => {
+            let ids = items
+                .iter()
+                .filter_map(|item| match item {
+                    SpotItem::Track(ft) => ft.id.clone(),
+                    _ => None,
+                })
+                .collect();
+            spot.save_tracks(ids).await
+        }
+    }
+}
+
+fn playlist_id(uri: &str) -> ClientResult<PlaylistId<'_>> {
+    PlaylistId::from_uri(uri)
+        .map_err(|e| ClientError::Io(io::Error::new(io::ErrorKind::InvalidData, e.to_string())))
+}
+
+/// Whether the request failed because Spotify could not be reached at all: no connection,
+/// a timeout or a server error. Error responses such as 404 are the caller's to report.
+pub fn is_offline_error(e: &ClientError) -> bool {
+    match e {
+        ClientError::Io(e) => matches!(
+            e.kind(),
+            io::ErrorKind::NotConnected | io::ErrorKind::TimedOut
+        ),
+        ClientError::Http(http) => match &**http {
+            HttpError::Client(e) => e.is_connect() || e.is_timeout(),
+            HttpError::StatusCode(response) => response.status().is_server_error(),
+        },
+        _ => false,
+    }
+}
+
+/// The error for requests which could not even start, e.g. without a session.
+pub fn offline_error(reason: impl ToString) -> ClientError {
+    ClientError::Io(io::Error::new(
+        io::ErrorKind::NotConnected,
+        reason.to_string(),
+    ))
+}
+
+fn load_queue() -> Vec<QueuedWrite> {
+    let Some(path) = cache_path("offline-queue.json") else {
+        return vec![];
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+            error!("Ignoring malformed offline queue in {:?}: {}", path, e);
+            vec![]
+        }),
+        Err(_) => vec![],
+    }
+}
+
+fn save_queue(queue: &[QueuedWrite]) {
+    let Some(path) = cache_path("offline-queue.json") else {
+        error!("Cannot save the offline queue: no cache directory");
+        return;
+    };
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, serde_json::to_string(queue).unwrap()));
+    if let Err(e) = result {
+        error!("Failed to save the offline queue to {:?}: {}", path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(code: u16) -> ClientError {
+        let response = http::Response::builder().status(code).body("").unwrap();
+        HttpError::StatusCode(rspotify_reqwest::Response::from(response)).into()
+    }
+
+    #[test]
+    fn server_errors_are_offline() {
+        assert!(is_offline_error(&status(500)));
+        assert!(is_offline_error(&status(503)));
+    }
+
+    #[test]
+    fn client_errors_are_not() {
+        assert!(!is_offline_error(&status(401)));
+        assert!(!is_offline_error(&status(404)));
+        assert!(!is_offline_error(&status(429)));
+    }
+
+    #[test]
+    fn io_errors() {
+        assert!(is_offline_error(&offline_error("no session")));
+        let timeout = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+        assert!(is_offline_error(&ClientError::Io(timeout)));
+        assert!(!is_offline_error(
+            &playlist_id("spotify:track:x").unwrap_err()
+        ));
+    }
+
+    #[tokio::test]
+    async fn refused_connection_is_offline() {
+        // Nobody listens on a port right after it was freed.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let e = rspotify_reqwest::get(format!("http://{}/", addr))
+            .await
+            .unwrap_err();
+        assert!(is_offline_error(&HttpError::Client(e).into()));
+    }
+}
@@ -0,0 +1,154 @@
+//! Persistent user settings, stored as JSON in the user config directory
+//! (usually ~/.config/russpot/settings.json).
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+
+/// A Connect device remembered across restarts. Spotify does not promise
+/// that device ids are stable, so the name is kept as a fallback.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreferredDevice {
+    pub id: String,
+    pub name: String,
+}
+
+/// An optional column of track lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Duration,
+    TrackNumber,
+    Album,
+    AddedAt,
+    AddedBy,
+    Explicit,
+    Popularity,
+}
+
+impl Column {
+    /// All the columns, in display order.
+    pub const ALL: [Column; 7] = [
+        Column::TrackNumber,
+        Column::Album,
+        Column::AddedAt,
+        Column::AddedBy,
+        Column::Explicit,
+        Column::Popularity,
+        Column::Duration,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Column::Duration => "Duration",
+            Column::TrackNumber => "Track number",
+            Column::Album => "Album",
+            Column::AddedAt => "Date added",
+            Column::AddedBy => "Added by",
+            Column::Explicit => "Explicit",
+            Column::Popularity => "Popularity",
+        }
+    }
+
+    /// Columns of a list kind the user did not choose any for.
+    pub fn defaults(kind: &str) -> Vec<Column> {
+        match kind {
+            "playlist" => vec![Column::AddedAt, Column::Duration],
+            "album" => vec![Column::TrackNumber, Column::Duration],
+            _ => vec![Column::Duration],
+        }
+    }
+}
+
+/// Light or dark colours.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeMode {
+    /// Follow the desktop preference.
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// The Connect device to start playback on. None means whichever device is active.
+    pub preferred_device: Option<PreferredDevice>,
+    /// Shell command to run on playback events of the local player, see hooks.rs.
+    pub on_event: Option<String>,
+    /// Columns shown in track lists, by list kind: playlist, album, show, recent, top, radio or search.
+    pub columns: HashMap<String, Vec<Column>>,
+    /// Whether the cursor steps over tracks which cannot be played in the user's country.
+    pub skip_unplayable: bool,
+    pub theme: ThemeMode,
+}
+
+impl Settings {
+    /// Returns a snapshot of the current settings.
+    pub fn get() -> Settings {
+        Settings::global().read().unwrap().clone()
+    }
+
+    /// Modifies the settings and writes them to disk.
+    pub fn update<F>(f: F)
+    where
+        F: FnOnce(&mut Settings),
+    {
+        let mut settings = Settings::global().write().unwrap();
+        f(&mut settings);
+        settings.save();
+    }
+
+    /// The columns to show in lists of the kind.
+    pub fn columns(&self, kind: &str) -> Vec<Column> {
+        self.columns
+            .get(kind)
+            .cloned()
+            .unwrap_or_else(|| Column::defaults(kind))
+    }
+
+    fn global() -> &'static RwLock<Settings> {
+        static SETTINGS: OnceLock<RwLock<Settings>> = OnceLock::new();
+        SETTINGS.get_or_init(|| RwLock::new(Settings::load()))
+    }
+
+    fn load() -> Settings {
+        let Some(path) = config_path("settings.json") else {
+            return Settings::default();
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                error!("Ignoring malformed settings in {:?}: {}", path, e);
+                Settings::default()
+            }),
+            Err(e) => {
+                debug!("No settings loaded from {:?}: {}", path, e);
+                Settings::default()
+            }
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = config_path("settings.json") else {
+            error!("Cannot save settings: no config directory");
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, serde_json::to_string_pretty(self).unwrap()));
+        if let Err(e) = result {
+            error!("Failed to save settings to {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Path of a file in the russpot config directory.
+pub fn config_path(file: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("russpot").join(file))
+}
@@ -2,87 +2,37 @@
 #![allow(unused_variables)]
 //! Package for fetching metadata over rspotify and controlling playback over librespot.
 //! TODO: Split out to separate files.
+pub mod librespot;
 pub mod model;
 
 use futures::TryStreamExt;
 use log::{debug, error};
 use std::collections::HashSet;
 
-use std::sync::{OnceLock, RwLock};
-use std::time::SystemTime;
+use std::sync::{Mutex, OnceLock};
 use std::{env, sync::Arc};
 
-use librespot::connect::spirc::Spirc;
-use librespot::core::config::ConnectConfig;
-use librespot::core::keymaster::Token;
-use librespot::playback::config::{AudioFormat, PlayerConfig};
-use librespot::playback::mixer::{MixerConfig, NoOpVolume};
-use librespot::playback::player::Player;
-use librespot::playback::{audio_backend, mixer};
-use librespot::{
-    core::{config::SessionConfig, session::Session},
-    discovery::Credentials,
-};
+use ::librespot::core::keymaster::Token;
+use ::librespot::core::session::Session;
 use rspotify::model::{
-    AlbumId, FullTrack, Offset, PlayContextId, PlayableItem, PlaylistId, SearchResult, SearchType,
-    SimplifiedPlaylist, TrackId,
+    AdditionalType, AlbumId, AlbumType, ArtistId, CurrentPlaybackContext, Device, EpisodeId,
+    FullArtist, FullEpisode, FullTrack, Market, PlayableId, PlayableItem, PlaylistId, SearchResult,
+    SearchType, ShowId, SimplifiedAlbum, SimplifiedPlaylist, SimplifiedShow, TimeRange, TrackId,
 };
-use rspotify::{clients::BaseClient, clients::OAuthClient, AuthCodeSpotify, Token as RSToken};
-use rspotify::{ClientResult, Config};
+use rspotify::prelude::*;
+use rspotify::{AuthCodeSpotify, Token as RSToken};
+use rspotify::{ClientError, ClientResult, Config};
 use tokio::sync::OnceCell;
 
-use self::model::SpotItem;
-
-struct LibreSpotConn {
-    session: Session,
-    player: RwLock<Option<Player>>, // RwLock because starting playback is considered a mutation.
-    spirc: Option<Spirc>,
-}
+use crate::hooks::Hooks;
+use crate::offline::offline_error;
+use crate::settings::Settings;
 
-impl LibreSpotConn {
-    async fn new() -> LibreSpotConn {
-        let user = env::var("RUSSPOT_USERNAME").expect("RUSSPOT_USERNAME env var must be set");
-        let pwd = env::var("RUSSPOT_PASSWORD").expect("RUSSPOT_PASSWORD env var must be set");
-        let credentials = Credentials::with_password(user, pwd);
-        let session_config = SessionConfig::default();
-        debug!("Connecting via LibreSpot using credentials from env vars...");
-        let t0 = SystemTime::now();
-        let (session, _session_credentials) =
-            Session::connect(session_config, credentials, None, false)
-                .await
-                .expect("LibreSpot Session failed");
-        debug!(
-            "LibreSpot connection established in {}ms. Creating player...",
-            SystemTime::now().duration_since(t0).unwrap().as_millis(),
-        );
-
-        let player_config = PlayerConfig::default();
-        let audio_format = AudioFormat::default();
-        let mut connect_config = ConnectConfig::default();
-        connect_config.name = "russpot".to_owned();
-        let backend = audio_backend::find(None).unwrap();
-        let (player, _player_event_channel) = Player::new(
-            player_config,
-            session.clone(),
-            Box::new(NoOpVolume),
-            move || backend(None, audio_format),
-        );
-        let mixer_factory = mixer::find(None).expect("mixer factory not found");
-        let mixer = mixer_factory(MixerConfig::default());
-        debug!("Starting Spirc connection...");
-        let (spirc, spirc_task) = Spirc::new(connect_config, session.clone(), player, mixer);
-        tokio::spawn(spirc_task); // let spirc run in the background
-        debug!(
-            "Spirc connection established, total {}ms.",
-            SystemTime::now().duration_since(t0).unwrap().as_millis()
-        );
-        LibreSpotConn {
-            session,
-            player: RwLock::new(None),
-            spirc: Some(spirc),
-        }
-    }
-}
+use self::librespot::{LibreSpotConfig, LibreSpotConn};
+use self::model::{
+    Added, ListEntry, ListHeader, PlaySource, PlayTarget, PlaylistDetails, PlaylistEdit, RadioSeed,
+    SpotItem, TrackDetails,
+};
 
 /// SpotConn encapsulates connection to Spotify.
 /// It's a bit like Arc: it is Clone, but the cloned
@@ -94,18 +44,32 @@ pub struct SpotConn {
     raw_librespot: Arc<OnceCell<LibreSpotConn>>,
     // raw_rspot is rspotify client using Spotify Web API, supporting user library and search.
     raw_rspot: AuthCodeSpotify,
+    /// Whether to register a local player in Spotify Connect when connecting.
+    local_player: bool,
+    /// The user's country, fetched on first use. None if the profile does not say.
+    market: Arc<OnceCell<Option<Market>>>,
+    /// The preferred device id from settings, and the id of the device it was last found as.
+    preferred_device: Arc<Mutex<Option<(String, String)>>>,
 }
 
+static SPOT_CONN: OnceLock<SpotConn> = OnceLock::new();
+
 impl SpotConn {
     /// Returns the global singleton instance of SpotConn.
     /// Note that the underlying connection may not exist yet,
     /// and will be established lazily on any method call.
     pub fn global() -> &'static SpotConn {
-        static SPOT_CONN: OnceLock<SpotConn> = OnceLock::new();
-        SPOT_CONN.get_or_init(SpotConn::new)
+        SPOT_CONN.get_or_init(|| SpotConn::new(true))
+    }
+
+    /// Like global(), but the connection never starts a local player.
+    /// For tools which only control playback on other devices.
+    /// Whichever of the two is called first decides for the whole process.
+    pub fn global_without_player() -> &'static SpotConn {
+        SPOT_CONN.get_or_init(|| SpotConn::new(false))
     }
 
-    fn new() -> Self {
+    fn new(local_player: bool) -> Self {
         let web_config = Config {
             token_refreshing: false,
             ..Default::default()
@@ -118,38 +82,67 @@ impl SpotConn {
         SpotConn {
             raw_librespot: Arc::new(OnceCell::new()),
             raw_rspot: web_api,
+            local_player,
+            market: Arc::new(OnceCell::new()),
+            preferred_device: Arc::new(Mutex::new(None)),
         }
     }
-    async fn librespot(&self) -> &LibreSpotConn {
+    /// Connects on first use. A failed connection is retried by the next call.
+    async fn librespot(&self) -> Result<&LibreSpotConn, String> {
         self.raw_librespot
-            .get_or_init(|| async { LibreSpotConn::new().await })
+            .get_or_try_init(|| async {
+                let config = LibreSpotConfig::default().with_env_credentials();
+                if !self.local_player {
+                    return LibreSpotConn::connect_session(config).await;
+                }
+                let (conn, tasks) = LibreSpotConn::connect(config).await?;
+                let hooks = Settings::get()
+                    .on_event
+                    .map(|command| Hooks::start(command, conn.session.clone()));
+                if let Some(hooks) = &hooks {
+                    hooks.forward(tasks.player_events);
+                }
+                // let spirc run in the background
+                tokio::spawn(async move {
+                    tasks.spirc_task.await;
+                    error!("LibreSpot session lost");
+                    if let Some(hooks) = hooks {
+                        hooks.session_lost();
+                    }
+                });
+                Ok(conn)
+            })
             .await
     }
-    async fn session(&self) -> &Session {
-        &self.librespot().await.session
+    async fn session(&self) -> Result<&Session, String> {
+        Ok(&self.librespot().await?.session)
     }
 
-    async fn get_new_token(&self) -> RSToken {
+    async fn get_new_token(&self) -> ClientResult<RSToken> {
         let client_id =
             env::var("RSPOTIFY_CLIENT_ID").expect("RSPOT_CLIENT_ID env var must be set"); // TODO: hardcode the Russpot ID here?
-        let scopes = "user-read-private,playlist-read-private,playlist-read-collaborative,playlist-modify-public,playlist-modify-private,user-follow-modify,user-follow-read,user-library-read,user-library-modify,user-top-read,user-read-recently-played,user-modify-playback-state,user-read-playback-state";
+        let scopes = "user-read-private,playlist-read-private,playlist-read-collaborative,playlist-modify-public,playlist-modify-private,user-follow-modify,user-follow-read,user-library-read,user-library-modify,user-top-read,user-read-recently-played,user-modify-playback-state,user-read-playback-state,user-read-playback-position";
         let url =
             format!("hm://keymaster/token/authenticated?client_id={client_id}&scope={scopes}");
-        let response = self.session().await.mercury().get(url).await;
-        let response = response.unwrap();
+        let session = self.session().await.map_err(offline_error)?;
+        let response = session
+            .mercury()
+            .get(url)
+            .await
+            .map_err(|e| offline_error(format!("keymaster request failed: {:?}", e)))?;
         let payload = response.payload.first().unwrap();
 
         let data = String::from_utf8(payload.clone()).unwrap();
-        let token: Token = serde_json::from_str(&data).unwrap();
-        RSToken {
+        let token: Token = serde_json::from_str(&data)?;
+        Ok(RSToken {
             access_token: token.access_token,
             expires_in: chrono::Duration::try_seconds(token.expires_in.into()).unwrap(),
             scopes: HashSet::from_iter(token.scope),
             expires_at: None,
             refresh_token: None,
-        }
+        })
     }
-    pub async fn rspot(&self) -> AuthCodeSpotify {
+    pub async fn rspot(&self) -> ClientResult<AuthCodeSpotify> {
         {
             // Check if OAuth token exists and is still valid, and refresh it if not.
             // This all happens under the token lock: we don't want other tasks
@@ -157,64 +150,78 @@ impl SpotConn {
             let locked = self.raw_rspot.token.lock();
             let mut rtok = locked.await.unwrap();
             if rtok.is_none() {
-                *rtok = Some(self.get_new_token().await);
+                *rtok = Some(self.get_new_token().await?);
             }
         }
-        self.raw_rspot.clone()
+        Ok(self.raw_rspot.clone())
+    }
+
+    /// The market to fetch everything for: the country in the user's profile.
+    /// Spotify then marks what cannot be played there, and relinks tracks to copies which can.
+    async fn market(&self) -> ClientResult<Option<Market>> {
+        let market = self
+            .market
+            .get_or_try_init(|| async {
+                let user = self.rspot().await?.me().await?;
+                debug!("Fetching metadata for market {:?}", user.country);
+                Ok::<_, ClientError>(user.country.map(Market::Country))
+            })
+            .await?;
+        Ok(*market)
     }
 
     /// Fetches all the user playlists and emits them via the consumer function asynchronously.
-    pub async fn current_user_playlists<F>(&self, f: F)
+    pub async fn current_user_playlists<F>(&self, f: F) -> ClientResult<()>
     where
         F: Fn(SimplifiedPlaylist),
     {
-        let spot = self.rspot().await;
+        let spot = self.rspot().await?;
 
         let mut stream = spot.current_user_playlists();
-        while let Some(simple_playlist) = stream.try_next().await.unwrap() {
+        while let Some(simple_playlist) = stream.try_next().await? {
             f(simple_playlist);
         }
+        Ok(())
     }
 
-    pub async fn current_user_playlists_until_shutdown<F>(
-        &self,
-        shutdown: relm4::ShutdownReceiver,
-        f: F,
-    ) where
-        F: Fn(SimplifiedPlaylist),
-    {
-        shutdown
-            .register(async { self.current_user_playlists(f).await })
-            .drop_on_shutdown()
-            .await
-    }
-
-    pub async fn tracks_in_playlist<F>(&self, shutdown: relm4::ShutdownReceiver, uri: String, f: F)
+    /// Emits the tracks and episodes in the playlist, with when and by whom they were added.
+    pub async fn playlist_items<F>(&self, uri: String, f: F) -> ClientResult<()>
     where
-        F: Fn(FullTrack),
+        F: Fn(ListEntry),
     {
-        shutdown
-            .register(async {
-                let rspot = self.rspot().await;
-                let mut stream = rspot.playlist_items(
-                    PlaylistId::from_uri(&uri).unwrap(),
-                    None, /*fields*/
-                    None, /*market*/
-                );
-                while let Some(item) = stream.try_next().await.unwrap() {
-                    match item.track {
-                        Some(PlayableItem::Track(ft)) => f(ft),
-                        _ => {
-                            debug!("Skipping non-track item {:?}", item,)
-                        }
-                    }
+        let rspot = self.rspot().await?;
+        let market = self.market().await?;
+        let mut stream = rspot.playlist_items(
+            PlaylistId::from_uri(&uri).unwrap(),
+            None, /*fields*/
+            market,
+        );
+        while let Some(item) = stream.try_next().await? {
+            let added = Added {
+                at: item.added_at,
+                by: item.added_by.as_ref().map(|user| {
+                    user.display_name
+                        .clone()
+                        .unwrap_or_else(|| user.id.id().to_string())
+                }),
+            };
+            let spot_item = match item.track {
+                Some(PlayableItem::Track(ft)) => SpotItem::Track(ft),
+                Some(PlayableItem::Episode(ep)) => SpotItem::Episode(ep),
+                None => {
+                    debug!("Skipping unavailable item {:?}", item);
+                    continue;
                 }
-            })
-            .drop_on_shutdown()
-            .await
+            };
+            f(ListEntry {
+                item: spot_item,
+                added: Some(added),
+            });
+        }
+        Ok(())
     }
 
-    pub async fn tracks_in_album<F>(&self, shutdown: relm4::ShutdownReceiver, uri: String, f: F)
+    pub async fn album_tracks<F>(&self, uri: String, f: F) -> ClientResult<()>
     where
         F: Fn(FullTrack),
     {
@@ -225,89 +232,529 @@ impl SpotConn {
         // We could reconstruct FullTrack from SimplifiedTrack+Album, but for now
         // let's just run a second API call to re-fetch the necessary items.
         // TODO: Optimize the second call away, perhaps introduce our own Track type.
-        shutdown
-            .register(async {
-                let rspot = self.rspot().await;
-                let mut stream = rspot.album_track(AlbumId::from_uri(&uri).unwrap(), None);
-                let mut track_ids: Vec<TrackId<'_>> = Vec::new();
-                while let Some(item) = stream.try_next().await.unwrap() {
-                    if let Some(id) = item.id {
-                        track_ids.push(id);
-                    }
-                }
-                match rspot.tracks(track_ids, None).await {
-                    ClientResult::Ok(tracks) => tracks.into_iter().for_each(f),
-                    ClientResult::Err(e) => {
-                        error!("Failed to load tracks in {}: {:?}", uri, e);
-                    }
-                }
-            })
-            .drop_on_shutdown()
-            .await
+        let rspot = self.rspot().await?;
+        let market = self.market().await?;
+        let mut stream = rspot.album_track(AlbumId::from_uri(&uri).unwrap(), market);
+        let mut track_ids: Vec<TrackId<'_>> = Vec::new();
+        while let Some(item) = stream.try_next().await? {
+            if let Some(id) = item.id {
+                track_ids.push(id);
+            }
+        }
+        rspot
+            .tracks(track_ids, market)
+            .await?
+            .into_iter()
+            .for_each(f);
+        Ok(())
+    }
+
+    /// Fetches the podcasts saved by the user.
+    pub async fn saved_shows<F>(&self, f: F) -> ClientResult<()>
+    where
+        F: Fn(SimplifiedShow),
+    {
+        let rspot = self.rspot().await?;
+        let mut stream = rspot.get_saved_show();
+        while let Some(saved) = stream.try_next().await? {
+            f(saved.show);
+        }
+        Ok(())
+    }
+
+    pub async fn show_episodes<F>(&self, uri: String, f: F) -> ClientResult<()>
+    where
+        F: Fn(FullEpisode),
+    {
+        // Same story as with albums: the show endpoint returns simplified episodes,
+        // which lack the show itself. Re-fetch them in batches as FullEpisodes.
+        let rspot = self.rspot().await?;
+        let market = self.market().await?;
+        let mut stream = rspot.get_shows_episodes(ShowId::from_uri(&uri).unwrap(), market);
+        let mut episode_ids: Vec<EpisodeId<'_>> = Vec::new();
+        while let Some(item) = stream.try_next().await? {
+            episode_ids.push(item.id);
+        }
+        // Spotify accepts at most 50 episodes per call.
+        for chunk in episode_ids.chunks(50) {
+            let episodes = rspot.get_several_episodes(chunk.to_vec(), market).await?;
+            episodes.into_iter().for_each(&f);
+        }
+        Ok(())
+    }
+
+    /// Emits the last 50 tracks the user listened to, most recent first.
+    pub async fn recently_played<F>(&self, f: F) -> ClientResult<()>
+    where
+        F: Fn(FullTrack),
+    {
+        let rspot = self.rspot().await?;
+        let page = rspot.current_user_recently_played(Some(50), None).await?;
+        page.items.into_iter().for_each(|ph| f(ph.track));
+        Ok(())
+    }
+
+    pub async fn top_tracks<F>(&self, range: TimeRange, f: F) -> ClientResult<()>
+    where
+        F: Fn(FullTrack),
+    {
+        let rspot = self.rspot().await?;
+        let mut stream = rspot.current_user_top_tracks(Some(range));
+        while let Some(track) = stream.try_next().await? {
+            f(track);
+        }
+        Ok(())
+    }
+
+    pub async fn top_artists<F>(&self, range: TimeRange, f: F) -> ClientResult<()>
+    where
+        F: Fn(FullArtist),
+    {
+        let rspot = self.rspot().await?;
+        let mut stream = rspot.current_user_top_artists(Some(range));
+        while let Some(artist) = stream.try_next().await? {
+            f(artist);
+        }
+        Ok(())
+    }
+
+    /// Emits albums and singles of the artist.
+    pub async fn artist_albums<F>(&self, uri: String, f: F) -> ClientResult<()>
+    where
+        F: Fn(SimplifiedAlbum),
+    {
+        let rspot = self.rspot().await?;
+        let market = self.market().await?;
+        let mut stream = rspot.artist_albums(
+            ArtistId::from_uri(&uri).unwrap(),
+            [AlbumType::Album, AlbumType::Single],
+            market,
+        );
+        while let Some(album) = stream.try_next().await? {
+            f(album);
+        }
+        Ok(())
     }
 
-    pub async fn search<F>(&self, st: SearchType, query: String, f: F)
+    /// Emits tracks recommended by Spotify for the seed.
+    pub async fn recommendations<F>(&self, seed: RadioSeed, f: F) -> ClientResult<()>
+    where
+        F: Fn(FullTrack),
+    {
+        // Recommendations come as SimplifiedTracks, so re-fetch them like album tracks.
+        let rspot = self.rspot().await?;
+        let market = self.market().await?;
+        let recommendations = rspot
+            .recommendations(
+                std::iter::empty(),
+                Some(seed.artists.clone()),
+                None::<Vec<&str>>,
+                Some(seed.tracks.clone()),
+                market,
+                Some(50),
+            )
+            .await?;
+        let track_ids: Vec<TrackId<'_>> = recommendations
+            .tracks
+            .into_iter()
+            .filter_map(|t| t.id)
+            .collect();
+        rspot
+            .tracks(track_ids, market)
+            .await?
+            .into_iter()
+            .for_each(f);
+        Ok(())
+    }
+
+    pub async fn search<F>(&self, st: SearchType, query: String, f: F) -> ClientResult<()>
     where
         F: Fn(SpotItem),
     {
-        let spot = self.rspot().await;
-        match spot.search(&query, st, None, None, None, None).await {
-            ClientResult::Err(e) => {
-                error!("Search failed: {:?}", e);
-            }
-            ClientResult::Ok(SearchResult::Tracks(tracks)) => tracks
+        let spot = self.rspot().await?;
+        let market = self.market().await?;
+        match spot.search(&query, st, market, None, None, None).await? {
+            SearchResult::Tracks(tracks) => tracks
                 .items
                 .into_iter()
                 .for_each(|track| f(SpotItem::Track(track))),
-            ClientResult::Ok(SearchResult::Playlists(playlists)) => playlists
+            SearchResult::Playlists(playlists) => playlists
                 .items
                 .into_iter()
                 .for_each(|playlist| f(SpotItem::Playlist(playlist))),
-            ClientResult::Ok(SearchResult::Albums(albums)) => {
+            SearchResult::Albums(albums) => {
                 albums.items.into_iter().for_each(|a| f(SpotItem::Album(a)))
             }
-            ClientResult::Ok(thing) => {
+            SearchResult::Artists(artists) => artists
+                .items
+                .into_iter()
+                .for_each(|a| f(SpotItem::Artist(a))),
+            SearchResult::Shows(shows) => {
+                shows.items.into_iter().for_each(|sh| f(SpotItem::Show(sh)))
+            }
+            thing => {
                 error!("Search not implemented for {:?}", thing);
             }
         };
+        Ok(())
+    }
+
+    /// Fetches the track, episode or collection with the URI.
+    /// None if the URI is of a kind russpot cannot show.
+    pub async fn lookup(&self, uri: &str) -> ClientResult<Option<SpotItem>> {
+        let rspot = self.rspot().await?;
+        let market = self.market().await?;
+        let item = if let Ok(id) = TrackId::from_uri(uri) {
+            SpotItem::Track(rspot.track(id, market).await?)
+        } else if let Ok(id) = EpisodeId::from_uri(uri) {
+            SpotItem::Episode(rspot.get_an_episode(id, market).await?)
+        } else if let Ok(id) = AlbumId::from_uri(uri) {
+            SpotItem::Album(model::simplify(&rspot.album(id, market).await?)?)
+        } else if let Ok(id) = PlaylistId::from_uri(uri) {
+            SpotItem::Playlist(model::simplify(&rspot.playlist(id, None, market).await?)?)
+        } else if let Ok(id) = ShowId::from_uri(uri) {
+            SpotItem::Show(model::simplify(&rspot.get_a_show(id, market).await?)?)
+        } else if let Ok(id) = ArtistId::from_uri(uri) {
+            SpotItem::Artist(rspot.artist(id).await?)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(item))
+    }
+
+    /// Fetches the description and followers of a playlist, or the label of an album.
+    /// Empty for anything else.
+    pub async fn list_header(&self, source: &SpotItem) -> ClientResult<ListHeader> {
+        let rspot = self.rspot().await?;
+        let market = self.market().await?;
+        match source {
+            SpotItem::Playlist(sp) => {
+                let playlist = rspot.playlist(sp.id.clone(), None, market).await?;
+                Ok(ListHeader {
+                    description: playlist
+                        .description
+                        .map(|d| model::plain_text(&d))
+                        .filter(|d| !d.is_empty()),
+                    followers: Some(playlist.followers.total),
+                    label: None,
+                })
+            }
+            SpotItem::Album(SimplifiedAlbum { id: Some(id), .. }) => {
+                let album = rspot.album(id.clone(), market).await?;
+                Ok(ListHeader {
+                    label: album.label,
+                    ..ListHeader::default()
+                })
+            }
+            _ => Ok(ListHeader::default()),
+        }
+    }
+
+    /// Fetches the album label, full artists and audio features of the track.
+    pub async fn track_details(&self, track: &FullTrack) -> ClientResult<TrackDetails> {
+        let rspot = self.rspot().await?;
+        let market = self.market().await?;
+        let album = match &track.album.id {
+            Some(id) => Some(rspot.album(id.clone(), market).await?),
+            None => None,
+        };
+        let artist_ids: Vec<ArtistId<'_>> =
+            track.artists.iter().filter_map(|a| a.id.clone()).collect();
+        let artists = if artist_ids.is_empty() {
+            vec![]
+        } else {
+            rspot.artists(artist_ids).await?
+        };
+        let features = match &track.id {
+            Some(id) => match rspot.track_features(id.clone()).await {
+                Ok(features) => Some(features),
+                Err(e) => {
+                    debug!("No audio features for {}: {:?}", id.uri(), e);
+                    None
+                }
+            },
+            None => None,
+        };
+        Ok(TrackDetails {
+            label: album.and_then(|a| a.label),
+            artists,
+            features,
+        })
+    }
+
+    /// Creates a new playlist owned by the current user, and fills it with the seed items.
+    pub async fn create_playlist(
+        &self,
+        details: &PlaylistDetails,
+        seed: Vec<PlayableId<'static>>,
+    ) -> ClientResult<SimplifiedPlaylist> {
+        let spot = self.rspot().await?;
+        let user = spot.me().await?;
+        let description = (!details.description.is_empty()).then_some(details.description.as_str());
+        let created = spot
+            .user_playlist_create(
+                user.id,
+                &details.name,
+                Some(details.public),
+                Some(details.collaborative),
+                description,
+            )
+            .await?;
+        // Spotify accepts at most 100 items per call.
+        for chunk in seed.chunks(100) {
+            spot.playlist_add_items(created.id.clone(), chunk.to_vec(), None)
+                .await?;
+        }
+        debug!(
+            "Created playlist {} with {} items",
+            created.id.uri(),
+            seed.len()
+        );
+        Ok(model::simplify(&created)?)
     }
 
-    pub async fn play_context(&self, ctx: PlayContextId<'_>, offset: Option<Offset>) {
+    /// Returns the description of a playlist. Only the full playlist object carries it.
+    pub async fn playlist_description(&self, id: PlaylistId<'_>) -> ClientResult<String> {
+        let market = self.market().await?;
+        let playlist = self.rspot().await?.playlist(id, None, market).await?;
+        Ok(playlist.description.unwrap_or_default())
+    }
+
+    /// Changes name, description or visibility of the playlist.
+    pub async fn change_playlist_details(
+        &self,
+        id: PlaylistId<'_>,
+        edit: &PlaylistEdit,
+    ) -> ClientResult<()> {
         self.rspot()
-            .await
-            .start_context_playback(
-                ctx, None,   /* device id*/
-                offset, /* offset */
-                None,   /* Position */
+            .await?
+            .playlist_change_detail(
+                id,
+                edit.name.as_deref(),
+                edit.public,
+                edit.description.as_deref(),
+                edit.collaborative,
             )
             .await
-            .expect("failed to start context playback")
+            .map(|_| ())
+    }
+
+    /// Spotify has no real playlist deletion: unfollowing your own playlist removes it
+    /// from the library, which is what the official clients do as well.
+    pub async fn delete_playlist(&self, id: PlaylistId<'_>) -> ClientResult<()> {
+        self.rspot().await?.playlist_unfollow(id).await
+    }
+
+    /// The playlists the user can add items to: their own and the collaborative ones.
+    pub async fn editable_playlists(&self) -> ClientResult<Vec<SimplifiedPlaylist>> {
+        let spot = self.rspot().await?;
+        let user = spot.me().await?;
+        let mut playlists = vec![];
+        let mut stream = spot.current_user_playlists();
+        while let Some(sp) = stream.try_next().await? {
+            if sp.owner.id == user.id || sp.collaborative {
+                playlists.push(sp);
+            }
+        }
+        Ok(playlists)
+    }
+
+    /// Appends the items to the playlist. Returns the new snapshot id.
+    pub async fn add_to_playlist(
+        &self,
+        id: PlaylistId<'_>,
+        items: Vec<PlayableId<'static>>,
+    ) -> ClientResult<String> {
+        let spot = self.rspot().await?;
+        let mut snapshot_id = String::new();
+        // Spotify accepts at most 100 items per call.
+        for chunk in items.chunks(100) {
+            snapshot_id = spot
+                .playlist_add_items(id.clone(), chunk.to_vec(), None)
+                .await?
+                .snapshot_id;
+        }
+        Ok(snapshot_id)
+    }
+
+    /// Removes every occurrence of the items from the playlist. Returns the new snapshot id.
+    pub async fn remove_from_playlist(
+        &self,
+        id: PlaylistId<'_>,
+        items: Vec<PlayableId<'static>>,
+    ) -> ClientResult<String> {
+        let spot = self.rspot().await?;
+        let mut snapshot_id = String::new();
+        for chunk in items.chunks(100) {
+            snapshot_id = spot
+                .playlist_remove_all_occurrences_of_items(id.clone(), chunk.to_vec(), None)
+                .await?
+                .snapshot_id;
+        }
+        Ok(snapshot_id)
+    }
+
+    /// Adds the tracks to the user's Liked Songs.
+    pub async fn save_tracks(&self, ids: Vec<TrackId<'static>>) -> ClientResult<()> {
+        let spot = self.rspot().await?;
+        // At most 50 per call here.
+        for chunk in ids.chunks(50) {
+            spot.current_user_saved_tracks_add(chunk.to_vec()).await?;
+        }
+        Ok(())
+    }
+
+    /// Adds the tracks and episodes to the playback queue, in order.
+    pub async fn add_to_queue(&self, items: Vec<PlayableId<'static>>) -> ClientResult<()> {
+        let spot = self.rspot().await?;
+        let device_id = self.preferred_device_id().await;
+        for item in items {
+            let result = spot.add_item_to_queue(item, device_id.as_deref()).await;
+            self.check_preferred_device(&device_id, &result);
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Starts playback on the preferred device, or on the active one if there is no preference.
+    pub async fn play_context(&self, target: PlayTarget) -> ClientResult<()> {
+        let spot = self.rspot().await?;
+        let device_id = self.preferred_device_id().await;
+        let result = match target.source {
+            PlaySource::Context(ctx) => {
+                spot.start_context_playback(
+                    ctx,
+                    device_id.as_deref(),
+                    target.offset,
+                    target.position,
+                )
+                .await
+            }
+            PlaySource::Tracks(ids) => {
+                spot.start_uris_playback(ids, device_id.as_deref(), target.offset, target.position)
+                    .await
+            }
+        };
+        self.check_preferred_device(&device_id, &result);
+        result
+    }
+
+    /// Returns what is playing now, on whichever device. None if nothing is.
+    pub async fn playback(&self) -> ClientResult<Option<CurrentPlaybackContext>> {
+        let market = self.market().await?;
+        self.rspot()
+            .await?
+            .current_playback(market, Some(&[AdditionalType::Episode]))
+            .await
+    }
+
+    pub async fn pause(&self) -> ClientResult<()> {
+        self.rspot().await?.pause_playback(None).await
+    }
+
+    pub async fn resume(&self) -> ClientResult<()> {
+        self.rspot().await?.resume_playback(None, None).await
+    }
+
+    pub async fn next(&self) -> ClientResult<()> {
+        self.rspot().await?.next_track(None).await
+    }
+
+    pub async fn previous(&self) -> ClientResult<()> {
+        self.rspot().await?.previous_track(None).await
+    }
+
+    /// Jumps to the position within the current track.
+    pub async fn seek(&self, position: chrono::Duration) -> ClientResult<()> {
+        self.rspot().await?.seek_track(position, None).await
+    }
+
+    /// Sets the volume of the active device, in percent.
+    pub async fn set_volume(&self, percent: u8) -> ClientResult<()> {
+        self.rspot().await?.volume(percent.min(100), None).await
+    }
+
+    /// Lists the Spotify Connect devices currently available to the user.
+    pub async fn devices(&self) -> ClientResult<Vec<Device>> {
+        self.rspot().await?.device().await
+    }
+
+    /// Moves the current playback to another device, without pausing or resuming it.
+    pub async fn transfer_playback(&self, device_id: &str) -> ClientResult<()> {
+        debug!("Transferring playback to {}", device_id);
+        self.rspot().await?.transfer_playback(device_id, None).await
+    }
+
+    /// Resolves the preferred device from settings against the devices available now.
+    /// The id is tried first, then the name, because ids change e.g. on reinstalls.
+    /// The device found is remembered until a request to it fails.
+    async fn preferred_device_id(&self) -> Option<String> {
+        let preferred = Settings::get().preferred_device?;
+        if let Some((setting, found)) = &*self.preferred_device.lock().unwrap() {
+            if *setting == preferred.id {
+                return Some(found.clone());
+            }
+        }
+        let devices = match self.devices().await {
+            Ok(devices) => devices,
+            Err(e) => {
+                error!("Could not list devices: {:?}", e);
+                return None;
+            }
+        };
+        let found = devices
+            .iter()
+            .find(|dev| dev.id.as_deref() == Some(preferred.id.as_str()))
+            .or_else(|| devices.iter().find(|dev| dev.name == preferred.name));
+        match found.and_then(|dev| dev.id.clone()) {
+            Some(id) => {
+                *self.preferred_device.lock().unwrap() = Some((preferred.id, id.clone()));
+                Some(id)
+            }
+            None => {
+                debug!("Preferred device {} is not available", preferred.name);
+                None
+            }
+        }
     }
 
+    /// Looks the preferred device up again next time, if it may be gone.
+    fn check_preferred_device<T>(&self, device_id: &Option<String>, result: &ClientResult<T>) {
+        if device_id.is_some() && result.is_err() {
+            *self.preferred_device.lock().unwrap() = None;
+        }
+    }
+
+    /// Transfers playback to the device of this very process.
     pub async fn play_on_spirc(&self) {
-        let librespot = self.librespot().await;
-        let spirc = librespot.spirc.as_ref();
-        if spirc.is_none() {
+        let librespot = match self.librespot().await {
+            Ok(librespot) => librespot,
+            Err(e) => {
+                error!("No local player: {}", e);
+                return;
+            }
+        };
+        if librespot.spirc.is_none() {
             return;
         }
-        // librespot.spirc.as_ref().unwrap().play();
-        // println!("spirc play!");
-
-        let spot = self.rspot().await;
-        match spot.device().await {
+        // Spirc announces the session's device id, which the Web API lists the device under.
+        let own_id = librespot.session.device_id().to_string();
+        match self.devices().await {
             Ok(devices) => {
-                println!("Available devides: {:?}", devices);
-                for dev in devices {
-                    if dev.name.contains("russpot") {
-                        println!("enabling russpot device {}", dev.id.clone().unwrap());
-                        match spot.transfer_playback(dev.id.as_ref().unwrap(), None).await {
-                            Ok(_) => println!("playback transferred"),
-                            Err(e) => println!("could not transfer: {:?}", e),
-                        }
+                debug!("Available devices: {:?}", devices);
+                if devices
+                    .iter()
+                    .any(|dev| dev.id.as_deref() == Some(own_id.as_str()))
+                {
+                    match self.transfer_playback(&own_id).await {
+                        Ok(_) => debug!("playback transferred"),
+                        Err(e) => error!("could not transfer: {:?}", e),
                     }
+                } else {
+                    error!("russpot ({}) is not among the available devices", own_id);
                 }
             }
-            Err(e) => println!("Could not list devices: {}", e),
+            Err(e) => error!("Could not list devices: {}", e),
         }
     }
 }
@@ -0,0 +1,155 @@
+//! Raw librespot connection: the session, the local player and the Spirc (Connect) handle.
+//! Shared by the GUI, which plays locally, and by the headless daemon.
+
+use std::env;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use librespot::connect::spirc::Spirc;
+use librespot::core::config::ConnectConfig;
+use librespot::playback::config::{AudioFormat, Bitrate, PlayerConfig};
+use librespot::playback::mixer::MixerConfig;
+use librespot::playback::player::{Player, PlayerEventChannel};
+use librespot::playback::{audio_backend, mixer};
+use librespot::{
+    core::{config::SessionConfig, session::Session},
+    discovery::Credentials,
+};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+/// How to log in, and how the local player shows up in Spotify Connect.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LibreSpotConfig {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Name of the player in Connect device lists.
+    pub device_name: String,
+    /// Audio backend, e.g. "pulseaudio" or "alsa". None picks the default backend.
+    pub backend: Option<String>,
+    /// Output device of the backend. None picks the default device.
+    pub audio_device: Option<String>,
+    /// Streaming bitrate in kbps: 96, 160 or 320.
+    pub bitrate: Option<String>,
+}
+
+impl Default for LibreSpotConfig {
+    fn default() -> Self {
+        LibreSpotConfig {
+            username: None,
+            password: None,
+            device_name: "russpot".to_owned(),
+            backend: None,
+            audio_device: None,
+            bitrate: None,
+        }
+    }
+}
+
+impl LibreSpotConfig {
+    /// Takes the credentials from RUSSPOT_USERNAME and RUSSPOT_PASSWORD env vars, when set.
+    pub fn with_env_credentials(mut self) -> Self {
+        if let Ok(user) = env::var("RUSSPOT_USERNAME") {
+            self.username = Some(user);
+        }
+        if let Ok(pwd) = env::var("RUSSPOT_PASSWORD") {
+            self.password = Some(pwd);
+        }
+        self
+    }
+}
+
+pub struct LibreSpotConn {
+    pub session: Session,
+    pub player: RwLock<Option<Player>>, // RwLock because starting playback is considered a mutation.
+    pub spirc: Option<Spirc>,
+}
+
+/// Background work of a LibreSpotConn, to be driven by the owner.
+/// The connection is dead once spirc_task completes, e.g. when the session drops.
+pub struct LibreSpotTasks {
+    pub spirc_task: Pin<Box<dyn Future<Output = ()> + Send>>,
+    pub player_events: PlayerEventChannel,
+}
+
+impl LibreSpotConn {
+    /// Logs in without a local player, for tools that only talk to the Web API.
+    pub async fn connect_session(config: LibreSpotConfig) -> Result<LibreSpotConn, String> {
+        let session = LibreSpotConn::login(config).await?;
+        Ok(LibreSpotConn {
+            session,
+            player: RwLock::new(None),
+            spirc: None,
+        })
+    }
+
+    /// Logs in and registers the local player in Spotify Connect.
+    pub async fn connect(
+        config: LibreSpotConfig,
+    ) -> Result<(LibreSpotConn, LibreSpotTasks), String> {
+        let mut player_config = PlayerConfig::default();
+        if let Some(bitrate) = &config.bitrate {
+            player_config.bitrate = Bitrate::from_str(bitrate)
+                .map_err(|_| format!("unsupported bitrate {}", bitrate))?;
+        }
+        let backend = audio_backend::find(config.backend.clone())
+            .ok_or_else(|| format!("unknown audio backend {:?}", config.backend))?;
+
+        let t0 = SystemTime::now();
+        let audio_device = config.audio_device.clone();
+        let connect_config = ConnectConfig {
+            name: config.device_name.clone(),
+            ..Default::default()
+        };
+        let session = LibreSpotConn::login(config).await?;
+        debug!("Creating player...");
+
+        let mixer_factory = mixer::find(None).expect("mixer factory not found");
+        let mixer = mixer_factory(MixerConfig::default());
+        let audio_format = AudioFormat::default();
+        let (player, player_events) = Player::new(
+            player_config,
+            session.clone(),
+            mixer.get_soft_volume(),
+            move || backend(audio_device, audio_format),
+        );
+        debug!("Starting Spirc connection...");
+        let (spirc, spirc_task) = Spirc::new(connect_config, session.clone(), player, mixer);
+        debug!(
+            "Spirc connection established, total {}ms.",
+            SystemTime::now().duration_since(t0).unwrap().as_millis()
+        );
+        let conn = LibreSpotConn {
+            session,
+            player: RwLock::new(None),
+            spirc: Some(spirc),
+        };
+        let tasks = LibreSpotTasks {
+            spirc_task: Box::pin(spirc_task),
+            player_events,
+        };
+        Ok((conn, tasks))
+    }
+
+    async fn login(config: LibreSpotConfig) -> Result<Session, String> {
+        let (Some(user), Some(pwd)) = (config.username, config.password) else {
+            return Err("username and password must be set".to_string());
+        };
+        let credentials = Credentials::with_password(user, pwd);
+        debug!("Connecting via LibreSpot...");
+        let t0 = SystemTime::now();
+        let (session, _session_credentials) =
+            Session::connect(SessionConfig::default(), credentials, None, false)
+                .await
+                .map_err(|e| format!("LibreSpot Session failed: {}", e))?;
+        debug!(
+            "LibreSpot connection established in {}ms.",
+            SystemTime::now().duration_since(t0).unwrap().as_millis(),
+        );
+        Ok(session)
+    }
+}
@@ -1,7 +1,10 @@
 use rspotify::model::{
-    FullTrack, PlayContextId, SearchType, SimplifiedAlbum, SimplifiedPlaylist,
+    AlbumId, ArtistId, AudioFeatures, Device, EpisodeId, FullArtist, FullEpisode, FullTrack,
+    Modality, Offset, PlayContextId, PlayableId, PlaylistId, SearchType, ShowId, SimplifiedAlbum,
+    SimplifiedPlaylist, SimplifiedShow, TimeRange, TrackId,
 };
 use rspotify::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 pub fn format_search_type(st: &SearchType) -> &str {
     match st {
@@ -14,15 +17,175 @@ pub fn format_search_type(st: &SearchType) -> &str {
     }
 }
 
-/// Either a single Spotify item (track) or a conceptual
-/// collection of items (playlist, album).
-#[derive(Clone)]
+pub fn format_time_range(tr: &TimeRange) -> &str {
+    match tr {
+        TimeRange::ShortTerm => "last 4 weeks",
+        TimeRange::MediumTerm => "last 6 months",
+        TimeRange::LongTerm => "all time",
+    }
+}
+
+/// Formats a duration like a player would: "3:07", or "1:02:45" if over an hour.
+pub fn format_duration(d: chrono::Duration) -> String {
+    let secs = d.num_seconds().max(0);
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+/// Formats a pitch class and mode like "F♯ minor".
+pub fn format_key(key: i32, mode: Modality) -> String {
+    const KEYS: [&str; 12] = [
+        "C", "C♯", "D", "D♯", "E", "F", "F♯", "G", "G♯", "A", "A♯", "B",
+    ];
+    let Some(name) = usize::try_from(key).ok().and_then(|k| KEYS.get(k)) else {
+        return "unknown".to_string();
+    };
+    match mode {
+        Modality::Major => format!("{} major", name),
+        Modality::Minor => format!("{} minor", name),
+        Modality::NoResult => name.to_string(),
+    }
+}
+
+/// Strips the tags and entities from a playlist description, which Spotify sends as HTML.
+pub fn plain_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    [
+        ("&quot;", "\""),
+        ("&#x27;", "'"),
+        ("&#39;", "'"),
+        ("&#x2F;", "/"),
+        ("&lt;", "<"),
+        ("&gt;", ">"),
+        ("&amp;", "&"),
+    ]
+    .iter()
+    .fold(text, |text, (entity, c)| text.replace(entity, c))
+}
+
+/// Turns a Spotify URI or a web link, like "https://open.spotify.com/track/XXX?si=YYY",
+/// into a URI like "spotify:track:XXX". None for anything else.
+pub fn parse_link(text: &str) -> Option<String> {
+    let text = text.trim();
+    let path = if let Some(uri) = text.strip_prefix("spotify:") {
+        uri.split(':').collect::<Vec<_>>()
+    } else {
+        let link = text
+            .strip_prefix("https://")
+            .or_else(|| text.strip_prefix("http://"))
+            .unwrap_or(text);
+        let link = link.strip_prefix("open.spotify.com/")?;
+        let link = link.split(['?', '#']).next().unwrap_or_default();
+        link.split('/')
+            // Localised links look like open.spotify.com/intl-de/track/XXX.
+            .filter(|part| !part.starts_with("intl-"))
+            .collect()
+    };
+    match path[..] {
+        [kind @ ("track" | "episode" | "album" | "playlist" | "show" | "artist"), id]
+            if !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            Some(format!("spotify:{}:{}", kind, id))
+        }
+        _ => None,
+    }
+}
+
+/// Either a single Spotify item (track, episode) or a conceptual
+/// collection of items (playlist, album, show, artist).
+#[derive(Clone, Serialize, Deserialize)]
 pub enum SpotItem {
     Track(FullTrack),
+    Episode(FullEpisode),
     Album(SimplifiedAlbum),
     Playlist(SimplifiedPlaylist),
+    Show(SimplifiedShow),
+    Artist(FullArtist),
     UserPlaylists,
-    SearchResults { st: SearchType, query: String },
+    SavedShows,
+    RecentlyPlayed,
+    TopTracks(TimeRange),
+    TopArtists(TimeRange),
+    Radio(RadioSeed),
+    /// A Spotify Connect device which can play music.
+    Device(Device),
+    /// All the Connect devices available to the user.
+    Devices,
+    SearchResults {
+        st: SearchType,
+        query: String,
+    },
+}
+
+/// An item of a list, with what the list knows about it beyond the item itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListEntry {
+    pub item: SpotItem,
+    /// When and by whom the item was added, for playlist items.
+    #[serde(default)]
+    pub added: Option<Added>,
+}
+
+impl From<SpotItem> for ListEntry {
+    fn from(item: SpotItem) -> Self {
+        ListEntry { item, added: None }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Added {
+    pub at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Display name of the user, or their id if they have none.
+    pub by: Option<String>,
+}
+
+/// Seeds for Spotify recommendations. Spotify accepts at most 5 seeds in total.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RadioSeed {
+    /// Name of the item the radio was started from.
+    pub name: String,
+    pub artists: Vec<ArtistId<'static>>,
+    pub tracks: Vec<TrackId<'static>>,
+}
+
+impl RadioSeed {
+    /// Builds the seed from a track, album or artist. None for anything else.
+    pub fn from_item(item: &SpotItem) -> Option<RadioSeed> {
+        let (artists, tracks) = match item {
+            SpotItem::Track(ft) => (
+                ft.artists.iter().filter_map(|a| a.id.clone()).collect(),
+                ft.id.clone().into_iter().collect(),
+            ),
+            SpotItem::Album(a) => (
+                a.artists.iter().filter_map(|a| a.id.clone()).collect(),
+                vec![],
+            ),
+            SpotItem::Artist(a) => (vec![a.id.clone()], vec![]),
+            _ => return None,
+        };
+        let mut seed = RadioSeed {
+            name: item.name(),
+            artists,
+            tracks,
+        };
+        // Tracks are the more specific seed, so keep all of them and cut down artists.
+        seed.tracks.truncate(5);
+        seed.artists.truncate(5 - seed.tracks.len());
+        Some(seed)
+    }
 }
 
 impl SpotItem {
@@ -30,16 +193,26 @@ impl SpotItem {
     pub fn name(&self) -> String {
         match self {
             SpotItem::Track(ft) => ft.name.clone(),
+            SpotItem::Episode(ep) => ep.name.clone(),
             SpotItem::Album(a) => a.name.clone(),
             SpotItem::Playlist(sp) => sp.name.clone(),
+            SpotItem::Show(sh) => sh.name.clone(),
+            SpotItem::Artist(a) => a.name.clone(),
             SpotItem::UserPlaylists => "Saved playlists".to_string(),
+            SpotItem::SavedShows => "Saved shows".to_string(),
+            SpotItem::RecentlyPlayed => "Recently played".to_string(),
+            SpotItem::TopTracks(tr) => format!("Top songs, {}", format_time_range(tr)),
+            SpotItem::TopArtists(tr) => format!("Top artists, {}", format_time_range(tr)),
+            SpotItem::Radio(seed) => format!("{} radio", seed.name),
+            SpotItem::Device(dev) => dev.name.clone(),
+            SpotItem::Devices => "Devices".to_string(),
             SpotItem::SearchResults { st, ref query } => {
                 format!("{}s matching {}", format_search_type(st), query)
             }
         }
     }
 
-    /// Name of the artist (or the owner user for playlists).
+    /// Name of the artist (or the owner user for playlists, the show for episodes).
     pub fn artist(&self) -> String {
         match self {
             SpotItem::Track(ft) => ft
@@ -48,6 +221,7 @@ impl SpotItem {
                 .map(|a| a.name.clone())
                 .collect::<Vec<String>>()
                 .join(", "),
+            SpotItem::Episode(ep) => ep.show.name.clone(),
             SpotItem::Album(a) => a
                 .artists
                 .iter()
@@ -55,7 +229,16 @@ impl SpotItem {
                 .collect::<Vec<String>>()
                 .join(", "),
             SpotItem::Playlist(sp) => sp.owner.display_name.clone().unwrap_or("".to_string()),
+            SpotItem::Show(sh) => sh.publisher.clone(),
+            SpotItem::Artist(a) => a.name.clone(),
             SpotItem::UserPlaylists => "You".to_string(),
+            SpotItem::SavedShows => "You".to_string(),
+            SpotItem::RecentlyPlayed => "You".to_string(),
+            SpotItem::TopTracks(_) => "You".to_string(),
+            SpotItem::TopArtists(_) => "You".to_string(),
+            SpotItem::Radio(_) => "Spotify".to_string(),
+            SpotItem::Device(_) => "".to_string(),
+            SpotItem::Devices => "You".to_string(),
             SpotItem::SearchResults { .. } => "".to_string(),
         }
     }
@@ -64,31 +247,116 @@ impl SpotItem {
     pub fn uri(&self) -> Option<String> {
         match self {
             SpotItem::Track(ft) => ft.id.as_ref().map(|id| id.uri()),
+            SpotItem::Episode(ep) => Some(ep.id.uri()),
             SpotItem::Album(a) => a.id.as_ref().map(|id| id.uri()),
             SpotItem::Playlist(sp) => Some(sp.id.uri()),
+            SpotItem::Show(sh) => Some(sh.id.uri()),
+            SpotItem::Artist(a) => Some(a.id.uri()),
             SpotItem::UserPlaylists => None,
+            SpotItem::SavedShows => None,
+            SpotItem::RecentlyPlayed => None,
+            SpotItem::TopTracks(_) => None,
+            SpotItem::TopArtists(_) => None,
+            SpotItem::Radio(_) => None,
+            SpotItem::Device(_) => None,
+            SpotItem::Devices => None,
             SpotItem::SearchResults { .. } => None,
         }
     }
 
+    /// The URI the item has in its album or playlist. Differs from uri() for tracks
+    /// which Spotify relinked to another copy playable in the user's market.
+    pub fn original_uri(&self) -> Option<String> {
+        match self {
+            SpotItem::Track(FullTrack {
+                linked_from: Some(link),
+                ..
+            }) => link.id.as_ref().map(|id| id.uri()).or_else(|| self.uri()),
+            _ => self.uri(),
+        }
+    }
+
+    /// Like playable_id(), but the id the playlist lists a relinked track under.
+    pub fn original_playable_id(&self) -> Option<PlayableId<'static>> {
+        match self {
+            SpotItem::Track(FullTrack {
+                linked_from: Some(link),
+                ..
+            }) => link
+                .id
+                .clone()
+                .map(PlayableId::Track)
+                .or_else(|| self.playable_id()),
+            _ => self.playable_id(),
+        }
+    }
+
+    /// Whether the item can be played in the user's market. True for collections,
+    /// and for items fetched without a market, when Spotify does not say.
+    pub fn is_playable(&self) -> bool {
+        match self {
+            SpotItem::Track(ft) => ft.is_playable.unwrap_or(true),
+            SpotItem::Episode(ep) => ep.is_playable,
+            _ => true,
+        }
+    }
+
     /// Returns an URL for the item or collection.
     pub fn href(&self) -> Option<&str> {
         match self {
             SpotItem::Track(ft) => ft.href.as_deref(),
+            SpotItem::Episode(ep) => Some(&ep.href),
             SpotItem::Album(a) => a.href.as_deref(),
             SpotItem::Playlist(sp) => Some(&sp.href),
+            SpotItem::Show(sh) => Some(&sh.href),
+            SpotItem::Artist(a) => Some(&a.href),
             SpotItem::UserPlaylists => None,
+            SpotItem::SavedShows => None,
+            SpotItem::RecentlyPlayed => None,
+            SpotItem::TopTracks(_) => None,
+            SpotItem::TopArtists(_) => None,
+            SpotItem::Radio(_) => None,
+            SpotItem::Device(_) => None,
+            SpotItem::Devices => None,
             SpotItem::SearchResults { .. } => None,
         }
     }
 
+    /// Returns the link to the item on open.spotify.com, the one to share with people.
+    /// Unlike href(), which points to the Web API.
+    pub fn web_url(&self) -> Option<String> {
+        let uri = self.uri()?;
+        let path = uri.strip_prefix("spotify:")?.replace(':', "/");
+        Some(format!("https://open.spotify.com/{}", path))
+    }
+
+    /// "Artist – Title" for tracks, episodes and albums, just the name for anything else.
+    pub fn share_line(&self) -> String {
+        match self {
+            SpotItem::Track(_) | SpotItem::Episode(_) | SpotItem::Album(_) => {
+                format!("{} – {}", self.artist(), self.name())
+            }
+            other => other.name(),
+        }
+    }
+
     /// Returns an URL to load the image from.
     pub fn img_url(&self) -> Option<&str> {
         match self {
             SpotItem::Track(ft) => &ft.album.images,
+            SpotItem::Episode(ep) => &ep.images,
             SpotItem::Album(a) => &a.images,
             SpotItem::Playlist(sp) => &sp.images,
+            SpotItem::Show(sh) => &sh.images,
+            SpotItem::Artist(a) => &a.images,
             SpotItem::UserPlaylists => return None,
+            SpotItem::SavedShows => return None,
+            SpotItem::RecentlyPlayed => return None,
+            SpotItem::TopTracks(_) => return None,
+            SpotItem::TopArtists(_) => return None,
+            SpotItem::Radio(_) => return None,
+            SpotItem::Device(_) => return None,
+            SpotItem::Devices => return None,
             SpotItem::SearchResults { .. } => return None,
         }
         .first()
@@ -96,17 +364,117 @@ impl SpotItem {
         .map(|img| img.url.as_str())
     }
 
-    /// Optional PlayContextId for the collection (for playlists and albums).
+    /// Optional PlayContextId for the collection (for playlists, albums, shows and artists).
     /// None if the collection cannot be played directly.
     pub fn context_id(&self) -> Option<PlayContextId<'_>> {
         match self {
             SpotItem::Track(_) => None,
+            SpotItem::Episode(_) => None,
             SpotItem::Album(a) => a.id.clone().map(PlayContextId::Album),
             SpotItem::Playlist(sp) => Some(PlayContextId::Playlist(sp.id.clone())),
+            SpotItem::Show(sh) => Some(PlayContextId::Show(sh.id.clone())),
+            SpotItem::Artist(a) => Some(PlayContextId::Artist(a.id.clone())),
             SpotItem::UserPlaylists => None,
+            SpotItem::SavedShows => None,
+            SpotItem::RecentlyPlayed => None,
+            SpotItem::TopTracks(_) => None,
+            SpotItem::TopArtists(_) => None,
+            SpotItem::Radio(_) => None,
+            SpotItem::Device(_) => None,
+            SpotItem::Devices => None,
             SpotItem::SearchResults { .. } => None,
         }
     }
+
+    /// Length of a track or episode. None for anything else.
+    pub fn duration(&self) -> Option<chrono::Duration> {
+        match self {
+            SpotItem::Track(ft) => Some(ft.duration),
+            SpotItem::Episode(ep) => Some(ep.duration),
+            _ => None,
+        }
+    }
+
+    /// Uri, name and artist as a JSON object, for scripts and other tools.
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "uri": self.uri(),
+            "name": self.name(),
+            "artist": self.artist(),
+        })
+    }
+
+    /// Id to play a single track or episode with. None for collections.
+    pub fn playable_id(&self) -> Option<PlayableId<'static>> {
+        match self {
+            SpotItem::Track(ft) => ft.id.clone().map(PlayableId::Track),
+            SpotItem::Episode(ep) => Some(PlayableId::Episode(ep.id.clone())),
+            _ => None,
+        }
+    }
+
+    /// Where to resume a partially played episode. None for anything else,
+    /// and for episodes which were not started or were played to the end.
+    pub fn resume_position(&self) -> Option<chrono::Duration> {
+        match self {
+            SpotItem::Episode(ep) => ep
+                .resume_point
+                .as_ref()
+                .filter(|rp| !rp.fully_played)
+                .map(|rp| rp.resume_position)
+                .filter(|pos| *pos > chrono::Duration::zero()),
+            _ => None,
+        }
+    }
+}
+
+/// What to play: either a context known to Spotify, or an ad-hoc list of tracks.
+#[derive(Debug, Clone)]
+pub enum PlaySource {
+    Context(PlayContextId<'static>),
+    Tracks(Vec<PlayableId<'static>>),
+}
+
+/// Where to start playback: a source, optionally an item in that source,
+/// and a position within that item.
+#[derive(Debug, Clone)]
+pub struct PlayTarget {
+    pub source: PlaySource,
+    pub offset: Option<Offset>,
+    pub position: Option<chrono::Duration>,
+}
+
+/// Starts at the index-th item of a context or a list of uris, even if an earlier item is the same.
+/// rspotify sends the milliseconds of the duration as the index.
+pub fn offset_at(index: usize) -> Offset {
+    Offset::Position(chrono::Duration::milliseconds(index as i64))
+}
+
+impl PlayTarget {
+    /// Parses the URI of anything playable. Tracks and episodes play on their own,
+    /// albums, playlists, shows and artists as a context.
+    pub fn from_uri(uri: &str, offset: Option<Offset>) -> Option<PlayTarget> {
+        let source = if let Ok(id) = TrackId::from_uri(uri) {
+            PlaySource::Tracks(vec![PlayableId::Track(id.into_static())])
+        } else if let Ok(id) = EpisodeId::from_uri(uri) {
+            PlaySource::Tracks(vec![PlayableId::Episode(id.into_static())])
+        } else if let Ok(id) = AlbumId::from_uri(uri) {
+            PlaySource::Context(PlayContextId::Album(id.into_static()))
+        } else if let Ok(id) = PlaylistId::from_uri(uri) {
+            PlaySource::Context(PlayContextId::Playlist(id.into_static()))
+        } else if let Ok(id) = ShowId::from_uri(uri) {
+            PlaySource::Context(PlayContextId::Show(id.into_static()))
+        } else if let Ok(id) = ArtistId::from_uri(uri) {
+            PlaySource::Context(PlayContextId::Artist(id.into_static()))
+        } else {
+            return None;
+        };
+        Some(PlayTarget {
+            source,
+            offset,
+            position: None,
+        })
+    }
 }
 
 impl std::fmt::Debug for SpotItem {
@@ -114,3 +482,215 @@ impl std::fmt::Debug for SpotItem {
         f.write_str(self.href().unwrap_or("unlinkable item"))
     }
 }
+
+/// Converts between two rspotify representations of the same object,
+/// for example FullPlaylist into SimplifiedPlaylist, via their common JSON form.
+/// The Web API returns the simplified fields as a subset of the full ones,
+/// so this only fails if the representations are unrelated.
+pub fn simplify<F: Serialize, S: DeserializeOwned>(full: &F) -> serde_json::Result<S> {
+    serde_json::from_value(serde_json::to_value(full)?)
+}
+
+/// User-editable details of a new playlist.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlaylistDetails {
+    pub name: String,
+    pub description: String,
+    pub public: bool,
+    pub collaborative: bool,
+}
+
+/// A change to the details of an existing playlist. Fields left as None are not modified.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlaylistEdit {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub public: Option<bool>,
+    pub collaborative: Option<bool>,
+}
+
+impl PlaylistEdit {
+    /// Applies the edit to a local copy of the playlist.
+    /// SimplifiedPlaylist has no description, so that one is skipped.
+    pub fn apply(&self, sp: &mut SimplifiedPlaylist) {
+        if let Some(name) = &self.name {
+            sp.name = name.clone();
+        }
+        if self.public.is_some() {
+            sp.public = self.public;
+        }
+        if let Some(collaborative) = self.collaborative {
+            sp.collaborative = collaborative;
+        }
+    }
+}
+
+/// What the header of a playlist or album page shows beyond the simplified object.
+#[derive(Debug, Clone, Default)]
+pub struct ListHeader {
+    pub description: Option<String>,
+    pub followers: Option<u32>,
+    /// Record label of an album.
+    pub label: Option<String>,
+}
+
+/// What the track details page shows beyond the FullTrack itself.
+#[derive(Debug, Clone)]
+pub struct TrackDetails {
+    /// Record label, from the full album.
+    pub label: Option<String>,
+    pub artists: Vec<FullArtist>,
+    /// Spotify does not serve audio features to every app, so these may be missing.
+    pub features: Option<AudioFeatures>,
+}
+
+/// An edit of the user library. Lists displaying the library apply these
+/// in place, so that they don't have to be reloaded.
+#[derive(Debug, Clone)]
+pub enum LibraryChange {
+    PlaylistCreated(SimplifiedPlaylist),
+    PlaylistUpdated(SimplifiedPlaylist),
+    /// The playlist with given URI was deleted (unfollowed).
+    PlaylistRemoved(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn artists(count: usize) -> Value {
+        (0..count)
+            .map(|n| json!({"external_urls": {}, "id": format!("artist{}", n), "name": format!("Artist {}", n)}))
+            .collect()
+    }
+
+    fn artist_id(n: usize) -> ArtistId<'static> {
+        ArtistId::from_id(format!("artist{}", n)).unwrap()
+    }
+
+    fn track(artist_count: usize) -> FullTrack {
+        serde_json::from_value(json!({
+            "album": {"artists": [], "external_urls": {}, "images": [], "name": "Album"},
+            "artists": artists(artist_count),
+            "disc_number": 1,
+            "duration_ms": 200000,
+            "explicit": false,
+            "external_ids": {},
+            "external_urls": {},
+            "id": "track0",
+            "is_local": false,
+            "name": "Song",
+            "popularity": 0,
+            "track_number": 1,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn radio_seed_from_track_has_the_track_and_its_artists() {
+        let seed = RadioSeed::from_item(&SpotItem::Track(track(2))).unwrap();
+        assert_eq!(seed.name, "Song");
+        assert_eq!(seed.tracks, vec![TrackId::from_id("track0").unwrap()]);
+        assert_eq!(seed.artists, vec![artist_id(0), artist_id(1)]);
+    }
+
+    #[test]
+    fn radio_seed_keeps_at_most_five_seeds() {
+        let seed = RadioSeed::from_item(&SpotItem::Track(track(6))).unwrap();
+        assert_eq!(seed.tracks.len(), 1);
+        assert_eq!(seed.artists, (0..4).map(artist_id).collect::<Vec<_>>());
+
+        let album: SimplifiedAlbum = serde_json::from_value(json!({
+            "artists": artists(7), "external_urls": {}, "images": [], "name": "Album",
+        }))
+        .unwrap();
+        let seed = RadioSeed::from_item(&SpotItem::Album(album)).unwrap();
+        assert!(seed.tracks.is_empty());
+        assert_eq!(seed.artists.len(), 5);
+    }
+
+    #[test]
+    fn radio_seed_from_artist_has_the_artist() {
+        let artist: FullArtist = serde_json::from_value(json!({
+            "external_urls": {},
+            "followers": {"total": 0},
+            "genres": [],
+            "href": "",
+            "id": "artist3",
+            "images": [],
+            "name": "Artist 3",
+            "popularity": 0,
+        }))
+        .unwrap();
+        let seed = RadioSeed::from_item(&SpotItem::Artist(artist)).unwrap();
+        assert_eq!(seed.name, "Artist 3");
+        assert_eq!(seed.artists, vec![artist_id(3)]);
+    }
+
+    #[test]
+    fn radio_seed_needs_a_track_album_or_artist() {
+        assert!(RadioSeed::from_item(&SpotItem::RecentlyPlayed).is_none());
+        assert!(RadioSeed::from_item(&SpotItem::UserPlaylists).is_none());
+    }
+
+    #[test]
+    fn parse_link_keeps_uris() {
+        assert_eq!(
+            parse_link("spotify:album:4m2880jivSbbyEGAKfITCa").as_deref(),
+            Some("spotify:album:4m2880jivSbbyEGAKfITCa")
+        );
+        assert_eq!(
+            parse_link("  spotify:track:0DiWol3AO6WpXZgp0goxAV\n").as_deref(),
+            Some("spotify:track:0DiWol3AO6WpXZgp0goxAV")
+        );
+    }
+
+    #[test]
+    fn parse_link_turns_web_links_into_uris() {
+        assert_eq!(
+            parse_link("https://open.spotify.com/track/0DiWol3AO6WpXZgp0goxAV?si=abc").as_deref(),
+            Some("spotify:track:0DiWol3AO6WpXZgp0goxAV")
+        );
+        assert_eq!(
+            parse_link("open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M#top").as_deref(),
+            Some("spotify:playlist:37i9dQZF1DXcBWIGoYBM5M")
+        );
+        assert_eq!(
+            parse_link("http://open.spotify.com/intl-de/show/5CfCWKI5pZ28U0uOzXkDHe").as_deref(),
+            Some("spotify:show:5CfCWKI5pZ28U0uOzXkDHe")
+        );
+    }
+
+    #[test]
+    fn parse_link_rejects_anything_else() {
+        assert_eq!(parse_link(""), None);
+        assert_eq!(parse_link("daft punk"), None);
+        assert_eq!(parse_link("spotify:user:someone"), None);
+        assert_eq!(parse_link("spotify:track:"), None);
+        assert_eq!(parse_link("spotify:track:abc:def"), None);
+        assert_eq!(
+            parse_link("https://example.com/track/0DiWol3AO6WpXZgp0goxAV"),
+            None
+        );
+        assert_eq!(
+            parse_link("https://open.spotify.com/track/0DiWol3A-O6WpXZ"),
+            None
+        );
+    }
+
+    #[test]
+    fn format_key_names_pitch_class_and_mode() {
+        assert_eq!(format_key(0, Modality::Major), "C major");
+        assert_eq!(format_key(6, Modality::Minor), "F♯ minor");
+        assert_eq!(format_key(11, Modality::NoResult), "B");
+    }
+
+    #[test]
+    fn format_key_is_unknown_without_a_key() {
+        // Spotify sends -1 when no key was detected.
+        assert_eq!(format_key(-1, Modality::Major), "unknown");
+        assert_eq!(format_key(12, Modality::Minor), "unknown");
+    }
+}
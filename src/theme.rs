@@ -0,0 +1,164 @@
+//! Styling: the built-in stylesheet with light or dark colours, and the user's own
+//! CSS from the config dir (usually ~/.config/russpot/style.css) layered on top.
+//! The colours follow the desktop preference, as told by the XDG desktop portal,
+//! and the user CSS is reloaded whenever the file changes.
+
+use std::path::{Path, PathBuf};
+
+use futures::StreamExt;
+use gtk::{gdk, gio, prelude::*};
+use log::{debug, error};
+use zbus::zvariant::{OwnedValue, Value};
+
+use crate::settings::{config_path, Settings, ThemeMode};
+
+const BASE_CSS: &str = include_str!("style.css");
+const LIGHT_COLORS: &str = include_str!("theme-light.css");
+const DARK_COLORS: &str = include_str!("theme-dark.css");
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const PORTAL_SETTINGS: &str = "org.freedesktop.portal.Settings";
+const APPEARANCE: &str = "org.freedesktop.appearance";
+const COLOR_SCHEME: &str = "color-scheme";
+
+pub struct Theme {
+    app: gtk::CssProvider,
+    user: gtk::CssProvider,
+    /// Whether GTK was set up dark before russpot changed it,
+    /// for when the portal has no preference.
+    gtk_dark: bool,
+    /// The preference from the portal, None if there is none.
+    portal_dark: Option<bool>,
+    /// Kept to go on watching the user CSS.
+    monitor: Option<gio::FileMonitor>,
+}
+
+impl Theme {
+    /// Styles the display with the built-in stylesheet and the user CSS.
+    pub fn install(display: &gdk::Display) -> Theme {
+        let gtk_settings = gtk::Settings::for_display(display);
+        let gtk_dark = gtk_settings.is_gtk_application_prefer_dark_theme()
+            || gtk_settings
+                .gtk_theme_name()
+                .is_some_and(|name| name.to_lowercase().ends_with("-dark"));
+
+        let app = gtk::CssProvider::new();
+        gtk::style_context_add_provider_for_display(
+            display,
+            &app,
+            gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
+        let user = gtk::CssProvider::new();
+        user.connect_parsing_error(|_, section, e| {
+            error!("In the user CSS at {}: {}", section, e);
+        });
+        gtk::style_context_add_provider_for_display(
+            display,
+            &user,
+            gtk::STYLE_PROVIDER_PRIORITY_USER,
+        );
+
+        let mut theme = Theme {
+            app,
+            user,
+            gtk_dark,
+            portal_dark: None,
+            monitor: None,
+        };
+        theme.apply();
+        if let Some(path) = config_path("style.css") {
+            load_user_css(&theme.user, &path);
+            theme.monitor = watch_user_css(&theme.user, path);
+        }
+        theme
+    }
+
+    /// Switches the colours to what the portal says the desktop prefers.
+    pub fn set_portal_dark(&mut self, dark: Option<bool>) {
+        debug!("desktop prefers dark: {:?}", dark);
+        self.portal_dark = dark;
+        self.apply();
+    }
+
+    fn is_dark(&self) -> bool {
+        match Settings::get().theme {
+            ThemeMode::Light => false,
+            ThemeMode::Dark => true,
+            ThemeMode::System => self.portal_dark.unwrap_or(self.gtk_dark),
+        }
+    }
+
+    /// Loads the stylesheet with the colours for the current preference,
+    /// and makes the GTK widgets match.
+    fn apply(&self) {
+        let dark = self.is_dark();
+        let colors = if dark { DARK_COLORS } else { LIGHT_COLORS };
+        self.app
+            .load_from_string(&format!("{}\n{}", colors, BASE_CSS));
+        if let Some(gtk_settings) = gtk::Settings::default() {
+            gtk_settings.set_gtk_application_prefer_dark_theme(dark);
+        }
+    }
+}
+
+fn load_user_css(provider: &gtk::CssProvider, path: &Path) {
+    if path.exists() {
+        debug!("Loading user CSS from {:?}", path);
+        provider.load_from_path(path);
+    } else {
+        provider.load_from_string("");
+    }
+}
+
+/// Reloads the user CSS whenever the file is written, created or removed.
+fn watch_user_css(provider: &gtk::CssProvider, path: PathBuf) -> Option<gio::FileMonitor> {
+    let monitor = gio::File::for_path(&path)
+        .monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE)
+        .map_err(|e| debug!("Not watching {:?}: {}", path, e))
+        .ok()?;
+    let provider = provider.clone();
+    monitor.connect_changed(move |_, _, _, event| {
+        // Writes come as several Changed events, followed by one ChangesDoneHint.
+        if event != gio::FileMonitorEvent::Changed {
+            load_user_css(&provider, &path);
+        }
+    });
+    Some(monitor)
+}
+
+/// Reports the desktop colour scheme preference now and on every change, until the
+/// session bus fails. Fails right away if there is no desktop portal.
+pub async fn watch_portal<F>(f: F) -> zbus::Result<()>
+where
+    F: Fn(Option<bool>),
+{
+    let conn = zbus::Connection::session().await?;
+    let proxy = zbus::Proxy::new(&conn, PORTAL_DESTINATION, PORTAL_PATH, PORTAL_SETTINGS).await?;
+    let mut changes = proxy.receive_signal("SettingChanged").await?;
+    let read_one: zbus::Result<OwnedValue> =
+        proxy.call("ReadOne", &(APPEARANCE, COLOR_SCHEME)).await;
+    // Older portals only have Read, which wraps the value once more.
+    let value: OwnedValue = match read_one {
+        Ok(value) => value,
+        Err(_) => proxy.call("Read", &(APPEARANCE, COLOR_SCHEME)).await?,
+    };
+    f(prefers_dark(&value));
+    while let Some(msg) = changes.next().await {
+        let (namespace, key, value): (String, String, OwnedValue) = msg.body().deserialize()?;
+        if namespace == APPEARANCE && key == COLOR_SCHEME {
+            f(prefers_dark(&value));
+        }
+    }
+    Ok(())
+}
+
+/// The portal says 1 for dark, 2 for light and 0 for no preference.
+fn prefers_dark(value: &Value) -> Option<bool> {
+    match value {
+        Value::Value(inner) => prefers_dark(inner),
+        Value::U32(1) => Some(true),
+        Value::U32(2) => Some(false),
+        _ => None,
+    }
+}
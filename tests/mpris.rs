@@ -0,0 +1,183 @@
+//! Runs the MPRIS service on a private session bus, and checks what a desktop widget sees.
+//! Skipped when dbus-daemon is not installed.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use futures::StreamExt;
+use russpot::mpris;
+use russpot::nowplaying::{NowPlaying, Playback, Track};
+use russpot::spotconn::SpotConn;
+use zbus::fdo::PropertiesProxy;
+use zbus::names::InterfaceName;
+use zbus::zvariant::{ObjectPath, OwnedValue};
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.russpot";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER: &str = "org.mpris.MediaPlayer2.Player";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A dbus-daemon of our own, killed when the test ends.
+struct Bus {
+    daemon: Child,
+    address: String,
+}
+
+impl Bus {
+    fn start() -> Option<Bus> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| eprintln!("No dbus-daemon, skipping: {}", e))
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take()?)
+            .read_line(&mut address)
+            .ok()?;
+        Some(Bus {
+            daemon,
+            address: address.trim().to_string(),
+        })
+    }
+
+    async fn connect(&self) -> zbus::Connection {
+        zbus::connection::Builder::address(self.address.as_str())
+            .unwrap()
+            .build()
+            .await
+            .unwrap()
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+fn track() -> Track {
+    Track {
+        uri: "spotify:track:0DiWol3AO6WpXZgp0goxAV".to_string(),
+        title: "One More Time".to_string(),
+        artists: vec!["Daft Punk".to_string()],
+        album: "Discovery".to_string(),
+        art_url: None,
+        length: Duration::from_secs(320),
+    }
+}
+
+fn player() -> InterfaceName<'static> {
+    InterfaceName::from_static_str_unchecked(PLAYER)
+}
+
+/// Waits until the service took its name on the bus.
+async fn properties(conn: &zbus::Connection) -> PropertiesProxy<'static> {
+    let props = PropertiesProxy::builder(conn)
+        .destination(BUS_NAME)
+        .unwrap()
+        .path(OBJECT_PATH)
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    for _ in 0..50 {
+        if props.get(player(), "PlaybackStatus").await.is_ok() {
+            return props;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("MPRIS service did not show up on the bus");
+}
+
+async fn playback_status(props: &PropertiesProxy<'_>) -> String {
+    let status = props.get(player(), "PlaybackStatus").await.unwrap();
+    String::try_from(status).unwrap()
+}
+
+#[tokio::test]
+async fn reports_playback_and_signals_changes() {
+    let Some(bus) = Bus::start() else {
+        return;
+    };
+    let now_playing: &'static NowPlaying = Box::leak(Box::new(NowPlaying::new()));
+    let service = zbus::connection::Builder::address(bus.address.as_str()).unwrap();
+    let server = tokio::spawn(mpris::serve_on(
+        service,
+        SpotConn::global_without_player(),
+        now_playing,
+    ));
+
+    let conn = bus.connect().await;
+    let props = properties(&conn).await;
+    assert_eq!(playback_status(&props).await, "Stopped");
+    let metadata = props.get(player(), "Metadata").await.unwrap();
+    let metadata: HashMap<String, OwnedValue> = metadata.try_into().unwrap();
+    let trackid: &ObjectPath = (&metadata["mpris:trackid"]).try_into().unwrap();
+    assert_eq!(
+        trackid.as_str(),
+        "/org/mpris/MediaPlayer2/TrackList/NoTrack"
+    );
+
+    let mut changes = props.receive_properties_changed().await.unwrap();
+    let seeks = zbus::Proxy::new(&conn, BUS_NAME, OBJECT_PATH, PLAYER)
+        .await
+        .unwrap();
+    let mut seeked = seeks.receive_signal("Seeked").await.unwrap();
+
+    // A track starts: both the status and the metadata change.
+    now_playing.publish(Playback::new(
+        true,
+        Some(track()),
+        Some(50),
+        Duration::from_secs(10),
+    ));
+    let mut changed = vec![];
+    while !(changed.contains(&"PlaybackStatus".to_string())
+        && changed.contains(&"Metadata".to_string()))
+    {
+        let signal = tokio::time::timeout(TIMEOUT, changes.next())
+            .await
+            .expect("no PropertiesChanged signal")
+            .unwrap();
+        let args = signal.args().unwrap();
+        assert_eq!(args.interface_name().as_str(), PLAYER);
+        changed.extend(args.changed_properties().keys().map(|key| key.to_string()));
+    }
+    assert_eq!(playback_status(&props).await, "Playing");
+    let metadata = props.get(player(), "Metadata").await.unwrap();
+    let metadata: HashMap<String, OwnedValue> = metadata.try_into().unwrap();
+    let title: &str = (&metadata["xesam:title"]).try_into().unwrap();
+    assert_eq!(title, "One More Time");
+    let trackid: &ObjectPath = (&metadata["mpris:trackid"]).try_into().unwrap();
+    assert_eq!(
+        trackid.as_str(),
+        "/io/github/wojciechp/russpot/track/0DiWol3AO6WpXZgp0goxAV"
+    );
+    let length: i64 = (&metadata["mpris:length"]).try_into().unwrap();
+    assert_eq!(length, 320_000_000);
+
+    // The same track, a minute further: a seek.
+    now_playing.publish(Playback::new(
+        true,
+        Some(track()),
+        Some(50),
+        Duration::from_secs(70),
+    ));
+    let signal = tokio::time::timeout(TIMEOUT, seeked.next())
+        .await
+        .expect("no Seeked signal")
+        .unwrap();
+    let position: i64 = signal.body().deserialize().unwrap();
+    assert!(
+        (70_000_000..71_000_000).contains(&position),
+        "Seeked to {}",
+        position
+    );
+
+    server.abort();
+}